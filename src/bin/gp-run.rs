@@ -0,0 +1,193 @@
+//! Runs GP on a CSV dataset from the command line, for batch experiments without a browser.
+//!
+//! ```text
+//! gp-run --data cubic.csv [--target y] [--config run.toml] [--seeds 1,2,3] [--out results]
+//!        [--checkpoint N] [--resume] [--quiet]
+//! ```
+//!
+//! Each seed writes `best.json` (the hall of fame), `stats.json` (per-generation statistics) and
+//! `population.json` (the final population) to `<out>/seed-<seed>/`, and `<out>/summary.json`
+//! lists the final result of every seed. With `--checkpoint N` the run is also saved to
+//! `state.json` every N generations, and `--resume` continues from it.
+
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
+use wasm_genetic_programming::{Config, Dataset, TickResult, GP};
+
+const USAGE: &str =
+    "usage: gp-run --data FILE.csv [--target COLUMN] [--config FILE.json|FILE.toml] \
+                     [--seeds 1,2,3] [--out DIR] [--checkpoint N] [--resume] [--quiet]";
+
+struct Options {
+    data: PathBuf,
+    target: Option<String>,
+    config: Option<PathBuf>,
+    seeds: Vec<u64>,
+    out: PathBuf,
+    checkpoint: Option<u16>,
+    resume: bool,
+    quiet: bool,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = parse_options(&args).and_then(run) {
+        eprintln!("gp-run: {}", e);
+        process::exit(1);
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        data: PathBuf::new(),
+        target: None,
+        config: None,
+        seeds: vec![1],
+        out: PathBuf::from("results"),
+        checkpoint: None,
+        resume: false,
+        quiet: false,
+    };
+    let mut data = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value\n{}", arg, USAGE))
+        };
+        match arg.as_str() {
+            "--data" => data = Some(PathBuf::from(value()?)),
+            "--target" => options.target = Some(value()?),
+            "--config" => options.config = Some(PathBuf::from(value()?)),
+            "--seeds" => {
+                options.seeds = value()?
+                    .split(',')
+                    .map(|seed| {
+                        seed.trim()
+                            .parse()
+                            .map_err(|_| format!("invalid seed: {}", seed))
+                    })
+                    .collect::<Result<_, _>>()?
+            }
+            "--out" => options.out = PathBuf::from(value()?),
+            "--checkpoint" => {
+                let every = value()?;
+                options.checkpoint = Some(
+                    every
+                        .parse()
+                        .ok()
+                        .filter(|every| *every > 0)
+                        .ok_or_else(|| format!("invalid checkpoint interval: {}", every))?,
+                )
+            }
+            "--resume" => options.resume = true,
+            "--quiet" => options.quiet = true,
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ => return Err(format!("unknown argument: {}\n{}", arg, USAGE)),
+        }
+    }
+    options.data = data.ok_or_else(|| format!("--data is required\n{}", USAGE))?;
+    Ok(options)
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let dataset = Dataset::from_csv(&read(&options.data)?, options.target.as_deref())?;
+    let config = match &options.config {
+        Some(path) => load_config(path)?,
+        None => Config::new(),
+    };
+
+    let mut summary: Vec<TickResult> = Vec::new();
+    for seed in &options.seeds {
+        let mut config = config.clone();
+        config.set_seed(Some(*seed));
+        if options.quiet {
+            config.set_log_level("off".to_string());
+        }
+        let dir = options.out.join(format!("seed-{}", seed));
+        fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        let state_path = dir.join("state.json");
+        let mut gp = if options.resume && state_path.exists() {
+            //the saved run keeps its own dataset and config, apart from --quiet
+            let mut gp = GP::restore_state(&read(&state_path)?)
+                .map_err(|e| format!("{}: {}", state_path.display(), e))?;
+            if options.quiet {
+                gp.set_log_level("off")?;
+            }
+            gp
+        } else {
+            let mut gp = GP::from_dataset(dataset.clone(), config)?;
+            gp.init();
+            gp
+        };
+        let result = loop {
+            let result = gp.step()?;
+            if options
+                .checkpoint
+                .is_some_and(|every| result.done || result.gen.is_multiple_of(every))
+            {
+                fs::write(&state_path, gp.save_state())
+                    .map_err(|e| format!("{}: {}", state_path.display(), e))?;
+            }
+            if !options.quiet {
+                eprintln!(
+                    "seed {} gen {} fitness {} best {}",
+                    seed,
+                    result.gen,
+                    result
+                        .fitness
+                        .map_or("-".to_string(), |fitness| fitness.to_string()),
+                    result.best
+                );
+            }
+            if result.done {
+                break result;
+            }
+        };
+
+        write_json(&dir.join("best.json"), &gp.hall_of_fame_entries())?;
+        write_json(&dir.join("stats.json"), &gp.generation_stats())?;
+        write_json(&dir.join("population.json"), &gp.population_entries())?;
+        println!(
+            "seed {}: {} after {} generations, fitness {}",
+            seed,
+            result.stop_reason.unwrap_or("done"),
+            result.gen,
+            result
+                .fitness
+                .map_or("-".to_string(), |fitness| fitness.to_string())
+        );
+        println!("  {}", result.best);
+        summary.push(result);
+    }
+    write_json(&options.out.join("summary.json"), &summary)
+}
+
+fn load_config(path: &Path) -> Result<Config, String> {
+    let text = read(path)?;
+    let json = if path
+        .extension()
+        .is_some_and(|extension| extension == "toml")
+    {
+        //go through JSON so both formats are read the same way
+        let value: serde_json::Value =
+            toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        value.to_string()
+    } else {
+        text
+    };
+    Config::parse(&json).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn write_json<T: serde::Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).unwrap();
+    fs::write(path, json).map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,491 @@
+use crate::dedup::Dedup;
+use crate::logging::LogLevel;
+use crate::selection;
+use crate::Primitive;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub(crate) pop_size: u32,
+    pub(crate) max_generations: u16,
+    pub(crate) mutate_prob: f32,
+    pub(crate) point_mutate_prob: f32,
+    pub(crate) hoist_mutate_prob: f32,
+    pub(crate) shrink_mutate_prob: f32,
+    pub(crate) selection: String,
+    pub(crate) tournament_size: u16,
+    pub(crate) elite_count: u16,
+    pub(crate) hall_of_fame_size: u16,
+    pub(crate) dedup: String,
+    pub(crate) semantic_tolerance: f64,
+    pub(crate) fitness_metric: String,
+    pub(crate) target_fitness: Option<f64>,
+    pub(crate) hit_tolerance: f64,
+    pub(crate) huber_delta: f64,
+    pub(crate) chromosome_function: Vec<String>,
+    pub(crate) chromosome_terminal: Vec<String>,
+    #[serde(skip)]
+    pub(crate) chromosome_combined: Vec<String>,
+    pub(crate) max_fitness_evals: u32,
+    pub(crate) tree_limit_initial: u16,
+    pub(crate) tree_limit_running: u16,
+    pub(crate) seed: Option<u64>,
+    pub(crate) max_time_ms: Option<f64>,
+    pub(crate) stagnation_limit: Option<u16>,
+    pub(crate) simplify_interval: Option<u16>,
+    pub(crate) log_level: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let chromosome_function: Vec<String> = ["+", "-", "*", "/", "sin", "cos", "exp"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        //filled in from the dataset columns when the run is created
+        let chromosome_terminal = Vec::new();
+        let chromosome_combined = [&chromosome_function[..], &chromosome_terminal[..]].concat();
+        Self {
+            pop_size: 4000,
+            max_generations: 51,
+            mutate_prob: 0.02,
+            point_mutate_prob: 0.02,
+            hoist_mutate_prob: 0.01,
+            shrink_mutate_prob: 0.01,
+            selection: "tournament".to_string(),
+            tournament_size: 6,
+            elite_count: 1,
+            hall_of_fame_size: 10,
+            dedup: "off".to_string(),
+            semantic_tolerance: 1e-6,
+            fitness_metric: "sae".to_string(),
+            target_fitness: None,
+            hit_tolerance: 0.01,
+            huber_delta: 1.0,
+            chromosome_function,
+            chromosome_terminal,
+            chromosome_combined,
+            max_fitness_evals: 20000,
+            tree_limit_initial: 6,
+            tree_limit_running: 17,
+            seed: None,
+            max_time_ms: None,
+            stagnation_limit: None,
+            simplify_interval: None,
+            log_level: "info".to_string(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Config {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads settings from a JSON object with the same field names as the getters. Missing
+    /// fields keep their default, unknown ones are an error.
+    pub fn from_json(json: &str) -> Result<Config, JsValue> {
+        Self::parse(json).map_err(|e| JsValue::from_str(&e))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn pop_size(&self) -> u32 {
+        self.pop_size
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_pop_size(&mut self, pop_size: u32) {
+        self.pop_size = pop_size;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn max_generations(&self) -> u16 {
+        self.max_generations
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_max_generations(&mut self, max_generations: u16) {
+        self.max_generations = max_generations;
+    }
+
+    /// Probability of subtree mutation, replacing a random subtree with a freshly generated one.
+    #[wasm_bindgen(getter)]
+    pub fn mutate_prob(&self) -> f32 {
+        self.mutate_prob
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_mutate_prob(&mut self, mutate_prob: f32) {
+        self.mutate_prob = mutate_prob;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn point_mutate_prob(&self) -> f32 {
+        self.point_mutate_prob
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_point_mutate_prob(&mut self, point_mutate_prob: f32) {
+        self.point_mutate_prob = point_mutate_prob;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn hoist_mutate_prob(&self) -> f32 {
+        self.hoist_mutate_prob
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_hoist_mutate_prob(&mut self, hoist_mutate_prob: f32) {
+        self.hoist_mutate_prob = hoist_mutate_prob;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn shrink_mutate_prob(&self) -> f32 {
+        self.shrink_mutate_prob
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_shrink_mutate_prob(&mut self, shrink_mutate_prob: f32) {
+        self.shrink_mutate_prob = shrink_mutate_prob;
+    }
+
+    /// One of "tournament", "roulette", "rank", "sus" or "random".
+    #[wasm_bindgen(getter)]
+    pub fn selection(&self) -> String {
+        self.selection.clone()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_selection(&mut self, selection: String) {
+        self.selection = selection;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn tournament_size(&self) -> u16 {
+        self.tournament_size
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_tournament_size(&mut self, tournament_size: u16) {
+        self.tournament_size = tournament_size;
+    }
+
+    /// The best members copied unchanged into every new generation.
+    #[wasm_bindgen(getter)]
+    pub fn elite_count(&self) -> u16 {
+        self.elite_count
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_elite_count(&mut self, elite_count: u16) {
+        self.elite_count = elite_count;
+    }
+
+    /// How many of the best distinct expressions of the whole run `GP::hall_of_fame` keeps.
+    #[wasm_bindgen(getter)]
+    pub fn hall_of_fame_size(&self) -> u16 {
+        self.hall_of_fame_size
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_hall_of_fame_size(&mut self, hall_of_fame_size: u16) {
+        self.hall_of_fame_size = hall_of_fame_size;
+    }
+
+    /// "off", "structural" to reject trees already in the population, or "semantic" to reject
+    /// trees whose outputs on the fitness cases match an existing member's.
+    #[wasm_bindgen(getter)]
+    pub fn dedup(&self) -> String {
+        self.dedup.clone()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_dedup(&mut self, dedup: String) {
+        self.dedup = dedup;
+    }
+
+    /// Outputs are compared after rounding to multiples of this, 0 compares them exactly.
+    #[wasm_bindgen(getter)]
+    pub fn semantic_tolerance(&self) -> f64 {
+        self.semantic_tolerance
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_semantic_tolerance(&mut self, semantic_tolerance: f64) {
+        self.semantic_tolerance = semantic_tolerance;
+    }
+
+    /// One of sae, mae, mse, rmse, nrmse, r2, max_error, huber or hits; r2 and hits are
+    /// maximised, the others minimised.
+    #[wasm_bindgen(getter)]
+    pub fn fitness_metric(&self) -> String {
+        self.fitness_metric.clone()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_fitness_metric(&mut self, fitness_metric: String) {
+        self.fitness_metric = fitness_metric;
+    }
+
+    /// The run stops once the best fitness reaches this value, in the units of `fitness_metric`;
+    /// when unset, 0.001 for error metrics, 0.999 for r2 and every case for hits.
+    #[wasm_bindgen(getter)]
+    pub fn target_fitness(&self) -> Option<f64> {
+        self.target_fitness
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_target_fitness(&mut self, target_fitness: Option<f64>) {
+        self.target_fitness = target_fitness;
+    }
+
+    /// Largest absolute error that still counts as a hit.
+    #[wasm_bindgen(getter)]
+    pub fn hit_tolerance(&self) -> f64 {
+        self.hit_tolerance
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_hit_tolerance(&mut self, hit_tolerance: f64) {
+        self.hit_tolerance = hit_tolerance;
+    }
+
+    /// Error at which the huber loss switches from squared to linear.
+    #[wasm_bindgen(getter)]
+    pub fn huber_delta(&self) -> f64 {
+        self.huber_delta
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_huber_delta(&mut self, huber_delta: f64) {
+        self.huber_delta = huber_delta;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn chromosome_function(&self) -> Vec<String> {
+        self.chromosome_function.clone()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_chromosome_function(&mut self, chromosome_function: Vec<String>) {
+        self.chromosome_function = chromosome_function;
+        self.update_chromosome_combined();
+    }
+
+    /// Dataset column names and "R" for a random constant; empty means every column plus "R".
+    #[wasm_bindgen(getter)]
+    pub fn chromosome_terminal(&self) -> Vec<String> {
+        self.chromosome_terminal.clone()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_chromosome_terminal(&mut self, chromosome_terminal: Vec<String>) {
+        self.chromosome_terminal = chromosome_terminal;
+        self.update_chromosome_combined();
+    }
+
+    /// The combined set is always the functions followed by the terminals, so it is read-only.
+    #[wasm_bindgen(getter)]
+    pub fn chromosome_combined(&self) -> Vec<String> {
+        self.chromosome_combined.clone()
+    }
+
+    /// The run stops, even mid-generation, once this many fitness evaluations have been made.
+    #[wasm_bindgen(getter)]
+    pub fn max_fitness_evals(&self) -> u32 {
+        self.max_fitness_evals
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_max_fitness_evals(&mut self, max_fitness_evals: u32) {
+        self.max_fitness_evals = max_fitness_evals;
+    }
+
+    /// Wall-clock limit measured from `GP::init`, unlimited when unset.
+    #[wasm_bindgen(getter)]
+    pub fn max_time_ms(&self) -> Option<f64> {
+        self.max_time_ms
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_max_time_ms(&mut self, max_time_ms: Option<f64>) {
+        self.max_time_ms = max_time_ms;
+    }
+
+    /// Stop after this many generations without the best fitness improving, unlimited when unset.
+    #[wasm_bindgen(getter)]
+    pub fn stagnation_limit(&self) -> Option<u16> {
+        self.stagnation_limit
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_stagnation_limit(&mut self, stagnation_limit: Option<u16>) {
+        self.stagnation_limit = stagnation_limit;
+    }
+
+    /// Every this many generations, members are replaced by their algebraically simplified form
+    /// when it measures the same fitness; unset leaves the population as evolved.
+    #[wasm_bindgen(getter)]
+    pub fn simplify_interval(&self) -> Option<u16> {
+        self.simplify_interval
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_simplify_interval(&mut self, simplify_interval: Option<u16>) {
+        self.simplify_interval = simplify_interval;
+    }
+
+    /// "off", "error", "warn", "info" for the start and end of the run, or "debug" for every
+    /// generation.
+    #[wasm_bindgen(getter)]
+    pub fn log_level(&self) -> String {
+        self.log_level.clone()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_log_level(&mut self, log_level: String) {
+        self.log_level = log_level;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn tree_limit_initial(&self) -> u16 {
+        self.tree_limit_initial
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_tree_limit_initial(&mut self, tree_limit_initial: u16) {
+        self.tree_limit_initial = tree_limit_initial;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn tree_limit_running(&self) -> u16 {
+        self.tree_limit_running
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_tree_limit_running(&mut self, tree_limit_running: u16) {
+        self.tree_limit_running = tree_limit_running;
+    }
+
+    /// When unset, each run draws its own seed; the one used is reported by `GP::seed` and `tick`.
+    #[wasm_bindgen(getter)]
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+}
+
+impl Config {
+    /// The Rust equivalent of `from_json`.
+    pub fn parse(json: &str) -> Result<Config, String> {
+        let mut config: Config = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        config.update_chromosome_combined();
+        Ok(config)
+    }
+
+    fn update_chromosome_combined(&mut self) {
+        self.chromosome_combined =
+            [&self.chromosome_function[..], &self.chromosome_terminal[..]].concat();
+    }
+
+    /// Checks that the settings describe a run that can actually be performed.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.pop_size == 0 {
+            return Err("pop_size must be greater than 0".to_string());
+        }
+        if self.max_generations == 0 {
+            return Err("max_generations must be greater than 0".to_string());
+        }
+        for (name, prob) in &[
+            ("mutate_prob", self.mutate_prob),
+            ("point_mutate_prob", self.point_mutate_prob),
+            ("hoist_mutate_prob", self.hoist_mutate_prob),
+            ("shrink_mutate_prob", self.shrink_mutate_prob),
+        ] {
+            if !(0.0..=1.0).contains(prob) {
+                return Err(format!("{} must be between 0 and 1, got {}", name, prob));
+            }
+        }
+        selection::from_config(self)?;
+        if self.tournament_size == 0 {
+            return Err("tournament_size must be greater than 0".to_string());
+        }
+        if self.elite_count as u32 > self.pop_size {
+            return Err(format!(
+                "elite_count ({}) must not exceed pop_size ({})",
+                self.elite_count, self.pop_size
+            ));
+        }
+        Dedup::from_config(self)?;
+        if self.semantic_tolerance.is_nan() || self.semantic_tolerance < 0.0 {
+            return Err(format!(
+                "semantic_tolerance must not be negative, got {}",
+                self.semantic_tolerance
+            ));
+        }
+        if self.target_fitness.is_some_and(f64::is_nan) {
+            return Err("target_fitness must be a number".to_string());
+        }
+        if self.hit_tolerance.is_nan() || self.hit_tolerance < 0.0 {
+            return Err(format!(
+                "hit_tolerance must not be negative, got {}",
+                self.hit_tolerance
+            ));
+        }
+        if self.huber_delta.is_nan() || self.huber_delta <= 0.0 {
+            return Err(format!(
+                "huber_delta must be greater than 0, got {}",
+                self.huber_delta
+            ));
+        }
+        if self.chromosome_function.is_empty() {
+            return Err("chromosome_function must not be empty".to_string());
+        }
+        for function_name in &self.chromosome_function {
+            if Primitive::from_name(function_name).is_none() {
+                return Err(format!("unknown function: {}", function_name));
+            }
+        }
+        if self.max_fitness_evals == 0 {
+            return Err("max_fitness_evals must be greater than 0".to_string());
+        }
+        if let Some(max_time_ms) = self.max_time_ms {
+            if max_time_ms.is_nan() || max_time_ms <= 0.0 {
+                return Err(format!(
+                    "max_time_ms must be greater than 0, got {}",
+                    max_time_ms
+                ));
+            }
+        }
+        if self.stagnation_limit == Some(0) {
+            return Err("stagnation_limit must be greater than 0".to_string());
+        }
+        if self.simplify_interval == Some(0) {
+            return Err("simplify_interval must be greater than 0".to_string());
+        }
+        LogLevel::from_config(self)?;
+        if self.tree_limit_initial == 0 {
+            return Err("tree_limit_initial must be greater than 0".to_string());
+        }
+        if self.tree_limit_initial > self.tree_limit_running {
+            return Err(format!(
+                "tree_limit_initial ({}) must not exceed tree_limit_running ({})",
+                self.tree_limit_initial, self.tree_limit_running
+            ));
+        }
+        Ok(())
+    }
+}
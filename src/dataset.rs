@@ -0,0 +1,166 @@
+use crate::utils;
+use wasm_bindgen::prelude::*;
+
+/// The fitness cases: rows of named input columns, each with a target value.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Dataset {
+    columns: Vec<String>,
+    //column-major, `targets.len()` values per column
+    inputs: Vec<f64>,
+    targets: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl Dataset {
+    /// `values` holds the rows one after another, each row being one value per column
+    /// followed by the target. Column names are identifiers (letters, digits and `_`, not
+    /// starting with a digit) so expressions using them can be read back, and cannot be `R`,
+    /// `NaN` or `inf`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(columns: Vec<String>, values: Vec<f64>) -> Result<Dataset, JsValue> {
+        Self::from_values(columns, values).map_err(|e| JsValue::from_str(&e))
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn columns(&self) -> Vec<String> {
+        self.columns.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn rows(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// Every column plus `R`, the random constant: the terminal set used when
+    /// `Config::chromosome_terminal` is left empty.
+    pub fn terminal_set(&self) -> Vec<String> {
+        let mut terminals = self.columns.clone();
+        terminals.push("R".to_string());
+        terminals
+    }
+}
+
+impl Dataset {
+    /// The Rust equivalent of `new`.
+    pub fn from_values(columns: Vec<String>, values: Vec<f64>) -> Result<Dataset, String> {
+        let stride = columns.len() + 1;
+        if values.is_empty() || !values.len().is_multiple_of(stride) {
+            return Err(format!(
+                "values must hold one or more rows of {} inputs and a target",
+                columns.len()
+            ));
+        }
+        for (index, name) in columns.iter().enumerate() {
+            if !utils::is_name(name) {
+                return Err(format!("invalid column name: {:?}", name));
+            }
+            if columns[..index].contains(name) {
+                return Err(format!("duplicate column name: {}", name));
+            }
+        }
+        let inputs = (0..columns.len())
+            .flat_map(|column| values.iter().skip(column).step_by(stride).cloned())
+            .collect();
+        let targets = values
+            .iter()
+            .skip(columns.len())
+            .step_by(stride)
+            .cloned()
+            .collect();
+        Ok(Dataset {
+            columns,
+            inputs,
+            targets,
+        })
+    }
+
+    /// Reads comma-separated values with a header row of column names. The column named
+    /// `target`, or the last column when `None`, holds the targets and the others the inputs.
+    pub fn from_csv(csv: &str, target: Option<&str>) -> Result<Dataset, String> {
+        let mut lines = csv
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+        let header: Vec<String> = match lines.next() {
+            Some((_, line)) => line
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .collect(),
+            None => return Err("the csv is empty".to_string()),
+        };
+        let target_index = match target {
+            Some(target) => header
+                .iter()
+                .position(|name| name == target)
+                .ok_or_else(|| format!("no column named {}", target))?,
+            None => header.len() - 1,
+        };
+        let mut columns = header.clone();
+        columns.remove(target_index);
+        let mut values = Vec::new();
+        for (index, line) in lines {
+            let row: Vec<&str> = line.split(',').map(str::trim).collect();
+            if row.len() != header.len() {
+                return Err(format!(
+                    "line {}: expected {} values, got {}",
+                    index + 1,
+                    header.len(),
+                    row.len()
+                ));
+            }
+            let mut row = row
+                .iter()
+                .map(|value| {
+                    value
+                        .parse::<f64>()
+                        .map_err(|_| format!("line {}: {:?} is not a number", index + 1, value))
+                })
+                .collect::<Result<Vec<f64>, String>>()?;
+            //inputs first, then the target
+            let target = row.remove(target_index);
+            values.extend(row);
+            values.push(target);
+        }
+        Self::from_values(columns, values)
+    }
+
+    /// A single input column named `x`, from the flat `[x, y, x, y, ...]` array taken by `GP::new`.
+    pub(crate) fn from_pairs(fitness_array: &[f64]) -> Dataset {
+        let pairs = &fitness_array[..fitness_array.len() / 2 * 2];
+        Dataset {
+            columns: vec!["x".to_string()],
+            inputs: pairs.iter().step_by(2).cloned().collect(),
+            targets: pairs.iter().skip(1).step_by(2).cloned().collect(),
+        }
+    }
+
+    //the rows one after another as `from_values` takes them
+    pub(crate) fn values(&self) -> Vec<f64> {
+        (0..self.rows())
+            .flat_map(|row| {
+                (0..self.columns.len())
+                    .map(move |column| self.column(column)[row])
+                    .chain(std::iter::once(self.targets[row]))
+            })
+            .collect()
+    }
+
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|column| column == name)
+    }
+
+    pub fn column_name(&self, index: usize) -> &str {
+        &self.columns[index]
+    }
+
+    pub fn targets(&self) -> &[f64] {
+        &self.targets
+    }
+
+    /// The value of the column at `index` for every row.
+    pub fn column(&self, index: usize) -> &[f64] {
+        let rows = self.targets.len();
+        &self.inputs[index * rows..(index + 1) * rows]
+    }
+}
@@ -0,0 +1,134 @@
+//! Duplicate elimination, chosen by `Config::dedup`.
+
+use crate::genome::{self, Gene};
+use crate::primitive::{Function, Primitive};
+use crate::{Action, Config, Member, GP};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+//after this many rejections in a row the population has too little diversity left to avoid
+//duplicates, so the next one is let in rather than retrying forever
+const MAX_CONSECUTIVE_REJECTIONS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Dedup {
+    Off,
+    /// Rejects trees that are the same up to the order of commutative arguments.
+    Structural,
+    /// Rejects trees whose outputs on every fitness case round to the same multiples of
+    /// `tolerance` as an existing member's. The outputs are hashed when a member is measured, so
+    /// checking costs no extra evaluation.
+    Semantic {
+        tolerance: f64,
+    },
+}
+
+impl Dedup {
+    pub(crate) fn from_config(config: &Config) -> Result<Dedup, String> {
+        match config.dedup.as_str() {
+            "off" => Ok(Dedup::Off),
+            "structural" => Ok(Dedup::Structural),
+            "semantic" => Ok(Dedup::Semantic {
+                tolerance: config.semantic_tolerance,
+            }),
+            _ => Err(format!("unknown dedup: {}", config.dedup)),
+        }
+    }
+
+    /// A hash of `outputs` for semantic dedup, 0 for the other modes which do not need it.
+    pub(crate) fn signature(self, outputs: &[f64]) -> u64 {
+        let tolerance = match self {
+            Dedup::Semantic { tolerance } => tolerance,
+            _ => return 0,
+        };
+        let mut hasher = DefaultHasher::new();
+        for output in outputs {
+            //NaN and infinities only match themselves
+            if output.is_finite() && tolerance > 0.0 {
+                ((output / tolerance).round() as i64).hash(&mut hasher);
+            } else {
+                (output + 0.0).to_bits().hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+}
+
+impl GP {
+    /// Adds `member` to `population` unless it duplicates a member already there.
+    pub(crate) fn insert_into_population(
+        &mut self,
+        member: Member,
+        population: &mut Vec<Member>,
+    ) -> bool {
+        if self.dedup != Dedup::Off {
+            let key = self.dedup_key(&member);
+            if !self.population_keys.insert(key) {
+                if self.consecutive_rejections < MAX_CONSECUTIVE_REJECTIONS {
+                    self.consecutive_rejections += 1;
+                    self.duplicates_rejected += 1;
+                    return false;
+                }
+                log!(
+                    self,
+                    Warn,
+                    "gen {}: {} duplicates rejected in a row, letting one in",
+                    self.gen,
+                    MAX_CONSECUTIVE_REJECTIONS
+                );
+            }
+        }
+        self.consecutive_rejections = 0;
+        population.push(member);
+        true
+    }
+
+    //forget the previous population's keys, remembering `members` which start the new one
+    pub(crate) fn reset_population_keys(&mut self, members: &[Member]) {
+        self.population_keys.clear();
+        self.consecutive_rejections = 0;
+        if self.dedup != Dedup::Off {
+            for member in members {
+                let key = self.dedup_key(member);
+                self.population_keys.insert(key);
+            }
+        }
+    }
+
+    //members are told apart by 64-bit hashes, so in the rare case of a collision a distinct
+    //member is rejected as a duplicate
+    fn dedup_key(&self, member: &Member) -> u64 {
+        match self.dedup {
+            Dedup::Semantic { .. } => member.signature,
+            _ => structural_hash(&member.chromosome),
+        }
+    }
+}
+
+fn structural_hash(genes: &[Gene]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    match genes[0].action {
+        Action::Function(function) => {
+            match function {
+                Function::Builtin(primitive) => (0u8, primitive).hash(&mut hasher),
+                Function::Custom(index) => (1u8, index).hash(&mut hasher),
+            }
+            let mut args: Vec<u64> = genome::args(genes).map(structural_hash).collect();
+            if is_commutative(function) {
+                args.sort_unstable();
+            }
+            args.hash(&mut hasher);
+        }
+        //adding 0.0 turns -0.0 into 0.0
+        Action::Terminal(value) => (2u8, (value + 0.0).to_bits()).hash(&mut hasher),
+        Action::Var(index) => (3u8, index).hash(&mut hasher),
+    }
+    hasher.finish()
+}
+
+fn is_commutative(function: Function) -> bool {
+    matches!(
+        function,
+        Function::Builtin(Primitive::Add | Primitive::Mul | Primitive::Min | Primitive::Max)
+    )
+}
@@ -0,0 +1,356 @@
+//! Source code generation, so an evolved model can be used without this library.
+//!
+//! Every language gets a self-contained function `model` taking one argument per dataset column,
+//! in column order. It computes exactly what the run evaluated: division is unguarded, so it
+//! gives infinities and NaN just as during the run (which penalised such members through
+//! `FitnessMetric::fitness`), `exp(a, b)` raises `a` to the power `b`, and `min` and `max` ignore
+//! a NaN argument like Rust's `f64::min` and `f64::max`. Transcendental functions come from each
+//! language's math library, which may differ from Rust's in the last bit.
+
+use crate::genome::{self, Gene};
+use crate::primitive::{Function, Kernel, Primitive};
+use crate::{Action, GP};
+use std::collections::BTreeSet;
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Language {
+    Rust,
+    JavaScript,
+    //vectorised over NumPy arrays
+    Python,
+    C,
+}
+
+impl Language {
+    fn from_name(name: &str) -> Result<Language, String> {
+        match name {
+            "rust" => Ok(Language::Rust),
+            "js" | "javascript" => Ok(Language::JavaScript),
+            "python" => Ok(Language::Python),
+            "c" => Ok(Language::C),
+            _ => Err(format!(
+                "unknown language: {}, expected rust, js, python or c",
+                name
+            )),
+        }
+    }
+
+    //names a parameter must not take: keywords and the names the generated code uses
+    fn reserved(self) -> &'static [&'static str] {
+        match self {
+            Language::Rust => &[
+                "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else",
+                "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match",
+                "mod", "model", "move", "mut", "pub", "ref", "return", "self", "Self", "static",
+                "struct", "super", "trait", "true", "try", "type", "unsafe", "use", "where",
+                "while", "yield",
+            ],
+            Language::JavaScript => &[
+                "arguments",
+                "await",
+                "break",
+                "case",
+                "catch",
+                "class",
+                "const",
+                "continue",
+                "debugger",
+                "default",
+                "delete",
+                "do",
+                "else",
+                "enum",
+                "eval",
+                "export",
+                "extends",
+                "false",
+                "finally",
+                "fmax",
+                "fmin",
+                "for",
+                "function",
+                "if",
+                "implements",
+                "import",
+                "in",
+                "Infinity",
+                "instanceof",
+                "interface",
+                "let",
+                "Math",
+                "model",
+                "NaN",
+                "new",
+                "null",
+                "package",
+                "pow",
+                "private",
+                "protected",
+                "public",
+                "return",
+                "static",
+                "super",
+                "switch",
+                "this",
+                "throw",
+                "true",
+                "try",
+                "typeof",
+                "undefined",
+                "var",
+                "void",
+                "while",
+                "with",
+                "yield",
+            ],
+            Language::Python => &[
+                "and", "as", "assert", "async", "await", "break", "class", "continue", "def",
+                "del", "elif", "else", "except", "False", "finally", "for", "from", "global", "if",
+                "import", "in", "is", "lambda", "model", "None", "nonlocal", "not", "np", "or",
+                "pass", "raise", "return", "True", "try", "while", "with", "yield",
+            ],
+            Language::C => &[
+                "auto", "break", "case", "char", "const", "continue", "cos", "default", "do",
+                "double", "else", "enum", "extern", "fabs", "float", "fmax", "fmin", "for", "goto",
+                "if", "INFINITY", "inline", "int", "log", "long", "model", "NAN", "pow",
+                "register", "restrict", "return", "short", "signed", "sin", "sizeof", "sqrt",
+                "static", "struct", "switch", "tanh", "typedef", "union", "unsigned", "void",
+                "volatile", "while",
+            ],
+        }
+    }
+
+    fn number(self, value: f64) -> String {
+        if value.is_finite() {
+            //Debug always has a decimal point or exponent, so C reads it as a double
+            return format!("{:?}", value);
+        }
+        let (nan, infinity) = match self {
+            Language::Rust => ("f64::NAN", "f64::INFINITY"),
+            Language::JavaScript => ("NaN", "Infinity"),
+            Language::Python => ("np.nan", "np.inf"),
+            Language::C => ("NAN", "INFINITY"),
+        };
+        match value {
+            _ if value.is_nan() => nan.to_string(),
+            _ if value > 0.0 => infinity.to_string(),
+            _ => format!("-{}", infinity),
+        }
+    }
+
+    fn call(self, primitive: Primitive, args: &[String]) -> String {
+        let name = match (self, primitive) {
+            //`/` on two Python floats raises ZeroDivisionError instead of giving inf or NaN
+            (Language::Python, Primitive::Div) => "np.divide",
+            (_, Primitive::Add)
+            | (_, Primitive::Sub)
+            | (_, Primitive::Mul)
+            | (_, Primitive::Div) => {
+                return format!("({} {} {})", args[0], primitive.name(), args[1]);
+            }
+            (Language::Rust, _) => match primitive {
+                Primitive::Exp => "f64::powf",
+                Primitive::Log => "f64::ln",
+                _ => return format!("f64::{}({})", primitive.name(), args.join(", ")),
+            },
+            (Language::JavaScript, Primitive::Exp) => "pow",
+            (Language::JavaScript, Primitive::Min) => "fmin",
+            (Language::JavaScript, Primitive::Max) => "fmax",
+            (Language::JavaScript, _) => return format!("Math.{}({})", primitive.name(), args[0]),
+            (Language::Python, Primitive::Exp) => "np.power",
+            (Language::Python, Primitive::Min) => "np.fmin",
+            (Language::Python, Primitive::Max) => "np.fmax",
+            (Language::Python, _) => return format!("np.{}({})", primitive.name(), args[0]),
+            (Language::C, Primitive::Exp) => "pow",
+            (Language::C, Primitive::Abs) => "fabs",
+            (Language::C, Primitive::Min) => "fmin",
+            (Language::C, Primitive::Max) => "fmax",
+            (Language::C, _) => primitive.name(),
+        };
+        format!("{}({})", name, args.join(", "))
+    }
+}
+
+#[wasm_bindgen]
+impl GP {
+    /// Source code of a function computing `expression` (infix or JSON, see `seed_expression`),
+    /// or the current best member when it is `null`. `language` is "rust", "js", "python" for
+    /// NumPy, or "c".
+    pub fn export_code(
+        &self,
+        language: &str,
+        expression: Option<String>,
+    ) -> Result<String, JsValue> {
+        self.generate_code(language, expression.as_deref())
+            .map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+impl GP {
+    /// The Rust equivalent of `export_code`.
+    pub fn generate_code(
+        &self,
+        language: &str,
+        expression: Option<&str>,
+    ) -> Result<String, String> {
+        let language = Language::from_name(language)?;
+        let chromosome = match expression {
+            Some(expression) => self.parse_chromosome(expression)?,
+            None => match self.population.last() {
+                Some(best) => best.chromosome.clone(),
+                None => return Err("there is no best member before init".to_string()),
+            },
+        };
+        let names = self.parameter_names(language);
+        let body = self.code_expression(&chromosome, language, &names)?;
+        let used: BTreeSet<usize> = chromosome
+            .iter()
+            .filter_map(|gene| match gene.action {
+                Action::Var(index) => Some(index),
+                _ => None,
+            })
+            .collect();
+        let printed = self.chromosome_to_string(&chromosome);
+
+        let code = match language {
+            Language::Rust => {
+                let parameters: Vec<String> = names
+                    .iter()
+                    .enumerate()
+                    .map(|(index, name)| {
+                        let unused = if used.contains(&index) || name.starts_with('_') {
+                            ""
+                        } else {
+                            "_"
+                        };
+                        format!("{}{}: f64", unused, name)
+                    })
+                    .collect();
+                format!(
+                    "/// {}\npub fn model({}) -> f64 {{\n    {}\n}}\n",
+                    printed,
+                    parameters.join(", "),
+                    body
+                )
+            }
+            Language::JavaScript => {
+                let mut helpers = String::new();
+                if self.calls(&chromosome, Primitive::Exp) {
+                    //Math.pow gives NaN where powf gives 1: a base of 1, or -1 to an infinite power
+                    helpers += "  const pow = (a, b) =>\n    a === 1 || (a === -1 && Math.abs(b) === Infinity) ? 1 : Math.pow(a, b);\n";
+                }
+                //Math.min and Math.max give NaN for a NaN argument, f64::min and f64::max ignore it
+                if self.calls(&chromosome, Primitive::Min) {
+                    helpers += "  const fmin = (a, b) => (isNaN(a) ? b : isNaN(b) ? a : Math.min(a, b));\n";
+                }
+                if self.calls(&chromosome, Primitive::Max) {
+                    helpers += "  const fmax = (a, b) => (isNaN(a) ? b : isNaN(b) ? a : Math.max(a, b));\n";
+                }
+                format!(
+                    "// {}\nfunction model({}) {{\n{}  return {};\n}}\n",
+                    printed,
+                    names.join(", "),
+                    helpers,
+                    body
+                )
+            }
+            Language::Python => {
+                let conversions: String = names
+                    .iter()
+                    .map(|name| format!("    {0} = np.asarray({0}, dtype=np.float64)\n", name))
+                    .collect();
+                //a constant model still returns one value per case
+                let result = if used.is_empty() {
+                    let shape = match names.len() {
+                        1 => format!("np.shape({})", names[0]),
+                        _ => format!("np.broadcast({}).shape", names.join(", ")),
+                    };
+                    format!("np.full({}, {})", shape, body)
+                } else {
+                    body
+                };
+                format!(
+                    "import numpy as np\n\n\ndef model({}):\n    \"\"\"{}\"\"\"\n{}    with np.errstate(all=\"ignore\"):\n        return {}\n",
+                    names.join(", "),
+                    printed,
+                    conversions,
+                    result
+                )
+            }
+            Language::C => {
+                let parameters: Vec<String> = names
+                    .iter()
+                    .map(|name| format!("double {}", name))
+                    .collect();
+                format!(
+                    "#include <math.h>\n\n/* {} */\ndouble model({}) {{\n    return {};\n}}\n",
+                    printed.replace("*/", "* /"),
+                    parameters.join(", "),
+                    body
+                )
+            }
+        };
+        Ok(code)
+    }
+
+    //the dataset columns as identifiers that are valid and distinct in `language`
+    fn parameter_names(&self, language: Language) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for column in self.dataset.columns() {
+            let mut name: String = column
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect();
+            if name.starts_with(|c: char| c.is_ascii_digit()) {
+                name.insert(0, '_');
+            }
+            while language.reserved().contains(&name.as_str()) || names.contains(&name) {
+                name.push('_');
+            }
+            names.push(name);
+        }
+        names
+    }
+
+    fn code_expression(
+        &self,
+        genes: &[Gene],
+        language: Language,
+        names: &[String],
+    ) -> Result<String, String> {
+        match genes[0].action {
+            Action::Function(function) => {
+                let args = genome::args(genes)
+                    .map(|arg| self.code_expression(arg, language, names))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let primitive = self.kernel(function)?;
+                Ok(language.call(primitive, &args))
+            }
+            Action::Terminal(value) => Ok(language.number(value)),
+            Action::Var(index) => Ok(names[index].clone()),
+        }
+    }
+
+    //the built-in primitive computing `function`
+    fn kernel(&self, function: Function) -> Result<Primitive, String> {
+        match function {
+            Function::Builtin(primitive) => Ok(primitive),
+            Function::Custom(index) => match &self.custom_primitives[index].kernel {
+                Kernel::Builtin(primitive) => Ok(*primitive),
+                Kernel::Js(_) => Err(format!(
+                    "{} is a JS primitive and cannot be exported",
+                    self.custom_primitives[index].name
+                )),
+            },
+        }
+    }
+
+    //whether `chromosome` calls `primitive`, directly or through a registered primitive
+    fn calls(&self, chromosome: &[Gene], primitive: Primitive) -> bool {
+        chromosome.iter().any(|gene| match gene.action {
+            Action::Function(function) => self.kernel(function) == Ok(primitive),
+            _ => false,
+        })
+    }
+}
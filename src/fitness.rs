@@ -0,0 +1,183 @@
+//! Fitness metrics, chosen by `Config::fitness_metric`, and the direction they are optimised in.
+
+use crate::Config;
+use std::cmp::Ordering;
+use wasm_bindgen::prelude::*;
+
+/// Whether lower or higher fitness is better, decided by the fitness metric.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Objective {
+    Minimize,
+    Maximize,
+}
+
+impl Objective {
+    /// Whether `a` is strictly better than `b`. NaN is never better than anything.
+    pub fn is_better(self, a: f64, b: f64) -> bool {
+        match self {
+            Objective::Minimize => a < b,
+            Objective::Maximize => a > b,
+        }
+    }
+
+    /// A fitness worse than any real score, given to members that do not evaluate to a
+    /// finite number.
+    pub fn worst(self) -> f64 {
+        match self {
+            Objective::Minimize => f64::INFINITY,
+            Objective::Maximize => f64::NEG_INFINITY,
+        }
+    }
+
+    /// Orders fitness from worst to best, NaN being the worst of all, so sorting with it
+    /// leaves the best member last.
+    pub fn compare(self, a: f64, b: f64) -> Ordering {
+        let (a, b) = (self.sanitize(a), self.sanitize(b));
+        match self {
+            Objective::Minimize => b.partial_cmp(&a).unwrap(),
+            Objective::Maximize => a.partial_cmp(&b).unwrap(),
+        }
+    }
+
+    /// Replaces NaN with `worst()` so it can be ordered.
+    pub fn sanitize(self, fitness: f64) -> f64 {
+        if fitness.is_nan() {
+            self.worst()
+        } else {
+            fitness
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FitnessMetric {
+    /// Sum of absolute errors.
+    Sae,
+    /// Mean absolute error.
+    Mae,
+    /// Mean squared error.
+    Mse,
+    /// Root mean squared error.
+    Rmse,
+    /// Root mean squared error divided by the standard deviation of the targets.
+    Nrmse,
+    /// Coefficient of determination, higher is better.
+    R2,
+    /// Largest absolute error.
+    MaxError,
+    /// Mean Huber loss: squared below `delta`, linear above it.
+    Huber { delta: f64 },
+    /// Number of cases predicted within `tolerance`, higher is better.
+    Hits { tolerance: f64 },
+}
+
+impl FitnessMetric {
+    pub fn from_config(config: &Config) -> Result<FitnessMetric, String> {
+        match config.fitness_metric.as_str() {
+            "sae" => Ok(FitnessMetric::Sae),
+            "mae" => Ok(FitnessMetric::Mae),
+            "mse" => Ok(FitnessMetric::Mse),
+            "rmse" => Ok(FitnessMetric::Rmse),
+            "nrmse" => Ok(FitnessMetric::Nrmse),
+            "r2" => Ok(FitnessMetric::R2),
+            "max_error" => Ok(FitnessMetric::MaxError),
+            "huber" => Ok(FitnessMetric::Huber {
+                delta: config.huber_delta,
+            }),
+            "hits" => Ok(FitnessMetric::Hits {
+                tolerance: config.hit_tolerance,
+            }),
+            _ => Err(format!("unknown fitness_metric: {}", config.fitness_metric)),
+        }
+    }
+
+    pub fn objective(self) -> Objective {
+        match self {
+            FitnessMetric::R2 | FitnessMetric::Hits { .. } => Objective::Maximize,
+            _ => Objective::Minimize,
+        }
+    }
+
+    /// The fitness at which a run is considered solved when `Config::target_fitness` is unset:
+    /// a perfect score for maximised metrics, close to zero error for the others.
+    pub fn default_target(self, cases: usize) -> f64 {
+        match self {
+            FitnessMetric::R2 => 0.999,
+            FitnessMetric::Hits { .. } => cases as f64,
+            _ => 0.001,
+        }
+    }
+
+    /// `evaluate`, except that NaN and infinities become `Objective::worst`, so members that
+    /// fail to evaluate rank below every real score whichever way the metric is optimised.
+    pub fn fitness(self, predictions: &[f64], targets: &[f64]) -> f64 {
+        let fitness = self.evaluate(predictions, targets);
+        if fitness.is_finite() {
+            fitness
+        } else {
+            self.objective().worst()
+        }
+    }
+
+    /// Scores `predictions` against `targets`, which have the same length.
+    pub fn evaluate(self, predictions: &[f64], targets: &[f64]) -> f64 {
+        let count = targets.len() as f64;
+        let errors = predictions.iter().zip(targets).map(|(p, t)| t - p);
+        match self {
+            FitnessMetric::Sae => errors.map(f64::abs).sum(),
+            FitnessMetric::Mae => errors.map(f64::abs).sum::<f64>() / count,
+            FitnessMetric::Mse => errors.map(|e| e * e).sum::<f64>() / count,
+            FitnessMetric::Rmse => (errors.map(|e| e * e).sum::<f64>() / count).sqrt(),
+            FitnessMetric::Nrmse => {
+                let rmse = (errors.map(|e| e * e).sum::<f64>() / count).sqrt();
+                let std_dev = std_dev(targets);
+                if std_dev > 0.0 {
+                    rmse / std_dev
+                } else {
+                    rmse
+                }
+            }
+            FitnessMetric::R2 => {
+                let residual: f64 = errors.map(|e| e * e).sum();
+                let mean = targets.iter().sum::<f64>() / count;
+                let total: f64 = targets.iter().map(|t| (t - mean).powi(2)).sum();
+                if total > 0.0 {
+                    1.0 - residual / total
+                } else if residual == 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            //fold with max would skip NaN, which must propagate to the penalty instead
+            FitnessMetric::MaxError => {
+                errors
+                    .map(f64::abs)
+                    .fold(0.0, |max, e| if e.is_nan() || e > max { e } else { max })
+            }
+            FitnessMetric::Huber { delta } => {
+                errors
+                    .map(|e| {
+                        let e = e.abs();
+                        if e <= delta {
+                            0.5 * e * e
+                        } else {
+                            delta * (e - 0.5 * delta)
+                        }
+                    })
+                    .sum::<f64>()
+                    / count
+            }
+            FitnessMetric::Hits { tolerance } => {
+                errors.filter(|e| e.abs() <= tolerance).count() as f64
+            }
+        }
+    }
+}
+
+fn std_dev(values: &[f64]) -> f64 {
+    let count = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / count;
+    (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count).sqrt()
+}
@@ -0,0 +1,92 @@
+//! Chromosomes stored as flat, prefix-ordered lists of genes.
+//!
+//! Each gene records the length of the subtree it roots, so any subtree is a contiguous slice
+//! `genes[index..index + genes[index].size]`, itself a valid chromosome. Crossover and mutation
+//! become slice splices instead of walking and cloning boxed trees.
+
+use crate::Action;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Gene {
+    pub(crate) action: Action,
+    //the number of genes in the subtree rooted here, including itself
+    pub(crate) size: usize,
+}
+
+pub(crate) type Genome = Vec<Gene>;
+
+impl Gene {
+    pub(crate) fn leaf(action: Action) -> Gene {
+        Gene { action, size: 1 }
+    }
+}
+
+/// A node applying `action` to `args`, each a complete subtree.
+pub(crate) fn node(action: Action, args: Vec<Genome>) -> Genome {
+    let size = 1 + args.iter().map(Vec::len).sum::<usize>();
+    let mut genes = Vec::with_capacity(size);
+    genes.push(Gene { action, size });
+    for arg in args {
+        genes.extend(arg);
+    }
+    genes
+}
+
+/// The subtree rooted at `index`.
+pub(crate) fn subtree(genes: &[Gene], index: usize) -> &[Gene] {
+    &genes[index..index + genes[index].size]
+}
+
+/// The subtrees of the root's arguments, in order.
+pub(crate) fn args(genes: &[Gene]) -> Args<'_> {
+    Args { genes, next: 1 }
+}
+
+pub(crate) struct Args<'a> {
+    genes: &'a [Gene],
+    next: usize,
+}
+
+impl<'a> Iterator for Args<'a> {
+    type Item = &'a [Gene];
+
+    fn next(&mut self) -> Option<&'a [Gene]> {
+        if self.next >= self.genes[0].size {
+            return None;
+        }
+        let arg = subtree(self.genes, self.next);
+        self.next += arg.len();
+        Some(arg)
+    }
+}
+
+/// A copy of `genes` with the subtree at `index` replaced by `replacement`.
+pub(crate) fn replace_subtree(genes: &[Gene], index: usize, replacement: &[Gene]) -> Genome {
+    let end = index + genes[index].size;
+    let mut spliced = Vec::with_capacity(genes.len() - (end - index) + replacement.len());
+    spliced.extend_from_slice(&genes[..index]);
+    spliced.extend_from_slice(replacement);
+    spliced.extend_from_slice(&genes[end..]);
+    //the ancestors of `index` are exactly the genes before it whose subtree reaches past it
+    for (ancestor, gene) in spliced[..index].iter_mut().enumerate() {
+        if ancestor + gene.size > index {
+            gene.size = gene.size + replacement.len() - (end - index);
+        }
+    }
+    spliced
+}
+
+/// The number of genes on the longest path from the root to a leaf.
+pub(crate) fn depth(genes: &[Gene]) -> u16 {
+    //the end of every subtree still open at the current gene
+    let mut open: Vec<usize> = Vec::new();
+    let mut depth = 0;
+    for (index, gene) in genes.iter().enumerate() {
+        while open.last().is_some_and(|end| *end <= index) {
+            open.pop();
+        }
+        open.push(index + gene.size);
+        depth = depth.max(open.len());
+    }
+    depth as u16
+}
@@ -0,0 +1,94 @@
+use crate::render::Format;
+use crate::{Member, GP};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// One of the best distinct expressions found during a run, see `GP::hall_of_fame`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HallOfFameEntry {
+    pub fitness: Option<f64>,
+    pub best: String,
+    pub chromosome: serde_json::Value,
+    //the generation it was first seen in
+    pub gen: u16,
+}
+
+pub(crate) struct Fame {
+    pub(crate) member: Member,
+    pub(crate) expression: String,
+    pub(crate) gen: u16,
+}
+
+#[wasm_bindgen]
+impl GP {
+    /// The best distinct expressions seen so far across the whole run, best first, at most
+    /// `Config::hall_of_fame_size` of them. See `HallOfFameEntry` for the fields. `format` picks
+    /// how `best` is printed, as for `format_expression`, "infix" by default.
+    pub fn hall_of_fame(&self, format: Option<String>) -> Result<JsValue, JsValue> {
+        let format = Format::from_name(format.as_deref().unwrap_or("infix"))
+            .map_err(|e| JsValue::from_str(&e))?;
+        Ok(self
+            .hall_of_fame_in(format)
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .unwrap())
+    }
+}
+
+impl GP {
+    /// The Rust equivalent of `hall_of_fame`, with infix expressions.
+    pub fn hall_of_fame_entries(&self) -> Vec<HallOfFameEntry> {
+        self.hall_of_fame_in(Format::Infix)
+    }
+
+    fn hall_of_fame_in(&self, format: Format) -> Vec<HallOfFameEntry> {
+        self.hall_of_fame
+            .iter()
+            .map(|fame| HallOfFameEntry {
+                fitness: Some(fame.member.fitness).filter(|fitness| fitness.is_finite()),
+                best: match format {
+                    Format::Infix => fame.expression.clone(),
+                    _ => self.render_chromosome(&fame.member.chromosome, format),
+                },
+                chromosome: self.chromosome_to_json(&fame.member.chromosome),
+                gen: fame.gen,
+            })
+            .collect()
+    }
+
+    //merge the current population, which is sorted best last, into the hall of fame
+    pub(crate) fn update_hall_of_fame(&mut self) {
+        let size = self.config.hall_of_fame_size as usize;
+        let objective = self.metric.objective();
+        let mut candidates = Vec::new();
+        for member in self.population.iter().rev() {
+            //penalised members never make it in
+            if candidates.len() == size || !member.fitness.is_finite() {
+                break;
+            }
+            //once the hall is full, the rest of the population cannot beat its last entry
+            if self.hall_of_fame.len() == size
+                && !objective.is_better(member.fitness, self.hall_of_fame[size - 1].member.fitness)
+            {
+                break;
+            }
+            let expression = self.chromosome_to_string(&member.chromosome);
+            let seen = self
+                .hall_of_fame
+                .iter()
+                .chain(candidates.iter())
+                .any(|fame: &Fame| fame.expression == expression);
+            if !seen {
+                candidates.push(Fame {
+                    member: member.clone(),
+                    expression,
+                    gen: self.gen,
+                });
+            }
+        }
+        self.hall_of_fame.extend(candidates);
+        //stable, so among equal fitness the earliest find stays ahead
+        self.hall_of_fame
+            .sort_by(|a, b| objective.compare(b.member.fitness, a.member.fitness));
+        self.hall_of_fame.truncate(size);
+    }
+}
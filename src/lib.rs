@@ -1,7 +1,61 @@
+// A macro to provide `log!(gp, Level, ..)`-style syntax for logging through `gp`'s logger,
+// formatting the message only when the level is enabled.
+macro_rules! log {
+    ( $gp:expr, $level:ident, $( $t:tt )* ) => {
+        if $gp.logger.enabled(crate::logging::LogLevel::$level) {
+            let message = format!( $( $t )* );
+            $gp.logger.write(crate::logging::LogLevel::$level, &message);
+        }
+    }
+}
+
+mod config;
+mod dataset;
+mod dedup;
+mod export;
+mod fitness;
+mod genome;
+mod hall_of_fame;
+mod logging;
+mod mutation;
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+mod parallel;
+mod parse;
+mod primitive;
+mod program;
+mod render;
+mod result;
+mod run;
+pub mod selection;
+mod simplify;
+mod state;
+mod stats;
 mod utils;
 
+pub use config::Config;
+pub use dataset::Dataset;
+pub use fitness::{FitnessMetric, Objective};
+pub use hall_of_fame::HallOfFameEntry;
+pub use logging::LogLevel;
+pub use primitive::Primitive;
+
+use dedup::Dedup;
+use genome::{Gene, Genome};
+use hall_of_fame::Fame;
+use logging::Logger;
+use primitive::{CustomPrimitive, Function};
+use program::Program;
+use render::Format;
+pub use result::{PopulationEntry, TickResult};
+pub use stats::GenerationStats;
+
+use selection::Selection;
+
 use rand::prelude::*;
-use std::cmp;
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
 use wasm_bindgen::prelude::*;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
@@ -12,546 +66,767 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
 extern crate web_sys;
 
-// A macro to provide `log!(..)`-style syntax for `console.log` logging.
-macro_rules! log {
-    ( $( $t:tt )* ) => {
-        web_sys::console::log_1(&format!( $( $t )* ).into());
-    }
+#[derive(Debug, Clone)]
+pub struct Member {
+    chromosome: Genome,
+    fitness: f64,
+    //hash of its outputs for semantic dedup, taken when it was measured
+    signature: u64,
 }
 
-pub struct Config {
-    pub pop_size: u16,
-    pub max_generations: u16,
-    pub mutate_prob: f32,
-    pub selection: String,
-    pub fitness_order: String,
-    pub chromosome_function: Vec<String>,
-    pub chromosome_terminal: Vec<String>,
-    pub chromosome_combined: Vec<String>,
-    pub max_fitness_evals: u16,
-    pub tree_limit_initial: u16,
-    pub tree_limit_running: u16,
+impl Member {
+    fn measured(chromosome: Genome, measurement: Measurement) -> Member {
+        Member {
+            chromosome,
+            fitness: measurement.fitness,
+            signature: measurement.signature,
+        }
+    }
 }
 
-// #[wasm_bindgen]
-// impl Config {
-//     #[wasm_bindgen(getter)]
-//     pub fn selection(&self) -> String {
-//         self.selection.clone()
-//     }
-
-//     #[wasm_bindgen(setter)]
-//     pub fn set_selection(&mut self, selection: String) {
-//         self.selection = selection;
-//     }
-// }
-
-#[derive(Debug, Clone)]
-pub struct Member {
-    chromosome: Node,
+/// What measuring a chromosome gives, see `GP::measure_fitness_batch`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Measurement {
     fitness: f64,
+    signature: u64,
 }
 
 #[wasm_bindgen]
 pub struct GP {
-    fitness: Vec<[f64; 2]>,
+    dataset: Dataset,
     config: Config,
+    functions: Vec<Function>,
+    custom_primitives: Vec<CustomPrimitive>,
+    terminals: Vec<Terminal>,
+    selection: Box<dyn Selection>,
+    metric: FitnessMetric,
+    target_fitness: f64,
+    seed: u64,
+    rng: Pcg32,
     fitness_evaluations: usize,
     gen: u16,
     population: Vec<Member>,
-    done: bool,
+    stop_reason: Option<StopReason>,
+    started_at: f64,
+    paused_at: Option<f64>,
+    best_fitness: f64,
+    last_improvement_gen: u16,
+    history: Vec<GenerationStats>,
+    hall_of_fame: Vec<Fame>,
+    dedup: Dedup,
+    //dedup keys of the population being built
+    population_keys: HashSet<u64>,
+    consecutive_rejections: usize,
+    //duplicates rejected since the last generation's stats were recorded
+    duplicates_rejected: usize,
+    logger: Logger,
+    //added with seed_expression, they start the initial population
+    seed_chromosomes: Vec<Genome>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum StopReason {
+    MaxGenerations,
+    TargetFitness,
+    MaxFitnessEvals,
+    MaxTime,
+    Stagnation,
+    Cancelled,
+}
+
+impl StopReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            StopReason::MaxGenerations => "max_generations",
+            StopReason::TargetFitness => "target_fitness",
+            StopReason::MaxFitnessEvals => "max_fitness_evals",
+            StopReason::MaxTime => "max_time",
+            StopReason::Stagnation => "stagnation",
+            StopReason::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Action {
-    Function(String),
+    Function(Function),
     Terminal(f64),
-    X,
+    //input column, by index into the dataset's columns
+    Var(usize),
 }
 
-#[wasm_bindgen]
-#[derive(Debug, Clone)]
-struct Node {
-    action: Action,
-    arg1: Option<Box<Node>>,
-    arg2: Option<Box<Node>>,
+//an entry in the competition between two parents and their children
+enum Candidate {
+    //index into GP::population
+    Parent(usize),
+    Child(Member),
+}
+
+impl Candidate {
+    fn fitness(&self, population: &[Member]) -> f64 {
+        match self {
+            Candidate::Parent(index) => population[*index].fitness,
+            Candidate::Child(member) => member.fitness,
+        }
+    }
+}
+
+//offspring planned by GP::plan_offspring, waiting for their fitness
+enum Offspring {
+    //a copy of a parent, with a new chromosome if mutation changed it
+    Reproduction {
+        parent: usize,
+        mutant: Option<Genome>,
+    },
+    Crossover {
+        parents: [usize; 2],
+        children: Vec<Genome>,
+        //how many of the parents and children go into the new population, 1 for the last slot
+        survivors: usize,
+    },
+}
+
+impl Offspring {
+    //the chromosomes that need measuring, in order
+    fn chromosomes(&self) -> impl Iterator<Item = &Genome> {
+        match self {
+            Offspring::Reproduction { mutant, .. } => mutant.iter().collect::<Vec<_>>(),
+            Offspring::Crossover { children, .. } => children.iter().collect(),
+        }
+        .into_iter()
+    }
+}
+
+//a resolved entry of Config::chromosome_terminal
+#[derive(Debug, Clone, Copy)]
+enum Terminal {
+    //"R", a random constant
+    Constant,
+    Var(usize),
 }
 
 #[wasm_bindgen]
 impl GP {
     pub fn new(fitness_array: Vec<f64>) -> Self {
-        let mut fitness: Vec<[f64; 2]> = Vec::new();
-        let mut pair: [f64; 2] = [0.0, 0.0];
-        for (index, value) in fitness_array.iter().enumerate() {
-            pair[index % 2] = *value;
-            if index % 2 == 1 {
-                fitness.push(pair);
-                pair = [0.0, 0.0];
-            }
-        }
+        Self::from_dataset(Dataset::from_pairs(&fitness_array), Config::default())
+            .expect("default config is valid")
+    }
 
-        Self {
-            fitness,
-            config: Config {
-                pop_size: 4000,
-                max_generations: 51,
-                mutate_prob: 0.02,
-                selection: "tournament".to_string(),
-                fitness_order: "desc".to_string(),
-                chromosome_function: ["+", "-", "*", "/", "sin", "cos", "exp"]
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect(),
-                chromosome_terminal: vec!["x".to_string(), "R".to_string()],
-                chromosome_combined: ["+", "-", "*", "/", "sin", "cos", "exp", "x", "R"]
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect(),
-                max_fitness_evals: 20000,
-                tree_limit_initial: 6,
-                tree_limit_running: 17,
-            },
-            fitness_evaluations: 0,
-            gen: 0,
-            population: Vec::new(),
-            done: false,
+    /// Creates a run using the given settings, rejecting settings that cannot be used.
+    pub fn with_config(fitness_array: Vec<f64>, config: &Config) -> Result<GP, JsValue> {
+        if fitness_array.len() < 2 || !fitness_array.len().is_multiple_of(2) {
+            return Err(JsValue::from_str(
+                "fitness_array must contain one or more [x, y] pairs",
+            ));
         }
+        Self::with_dataset(&Dataset::from_pairs(&fitness_array), config)
+    }
+
+    /// Creates a run over a dataset with any number of input columns.
+    pub fn with_dataset(dataset: &Dataset, config: &Config) -> Result<GP, JsValue> {
+        Self::from_dataset(dataset.clone(), config.clone()).map_err(|e| JsValue::from_str(&e))
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn config(&self) -> Config {
+        self.config.clone()
+    }
+
+    /// The seed this run was started with, either `Config::seed` or one drawn from entropy.
+    #[wasm_bindgen(getter)]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Whether this run looks for the lowest or the highest fitness, set by `Config::fitness_metric`.
+    #[wasm_bindgen(getter)]
+    pub fn objective(&self) -> Objective {
+        self.metric.objective()
     }
 
     pub fn init(&mut self) {
         utils::set_panic_hook();
+        self.started_at = utils::now_ms();
         //make initial random population
         let mut population: Vec<Member> = Vec::new();
+        self.reset_population_keys(&population);
+        let pop_size = self.config.pop_size as usize;
         let mut i = 0;
-        loop {
-            //call generate_chromosome with half false, half true (for Ramped Half and Half)
-            let chromosome = self.generate_chromosome(i > self.config.pop_size / 2);
-            let member = Member {
-                fitness: self.measure_fitness(&chromosome),
-                chromosome,
-            };
-            if self.insert_into_population(member, &mut population) {
+        //seed expressions go in first, as far as the population and budget allow
+        let seed_count = self
+            .seed_chromosomes
+            .len()
+            .min(pop_size)
+            .min(self.remaining_fitness_evals());
+        let seeds: Vec<Genome> = self.seed_chromosomes[..seed_count].to_vec();
+        let measurements = self.measure_fitness_batch(&seeds.iter().collect::<Vec<_>>());
+        for (chromosome, measurement) in seeds.into_iter().zip(measurements) {
+            if self
+                .insert_into_population(Member::measured(chromosome, measurement), &mut population)
+            {
                 i += 1;
             }
-
-            if i >= self.config.pop_size {
+        }
+        //generate a batch for the missing members, then measure it all at once
+        while i < pop_size {
+            //a small budget may run out before the population is full
+            let batch_size = (pop_size - i).min(self.remaining_fitness_evals());
+            if batch_size == 0 {
                 break;
             }
+            //call generate_chromosome with half false, half true (for Ramped Half and Half)
+            let chromosomes: Vec<Genome> = (0..batch_size)
+                .map(|slot| self.generate_chromosome(i + slot > pop_size / 2))
+                .collect();
+            let measurements = self.measure_fitness_batch(&chromosomes.iter().collect::<Vec<_>>());
+            for (chromosome, measurement) in chromosomes.into_iter().zip(measurements) {
+                if self.insert_into_population(
+                    Member::measured(chromosome, measurement),
+                    &mut population,
+                ) {
+                    i += 1;
+                }
+            }
         }
         self.population = population;
+        self.sort_population();
+        self.best_fitness = self.population.last().unwrap().fitness;
+        self.update_hall_of_fame();
+        self.record_generation_stats();
+        log!(
+            self,
+            Info,
+            "run started: seed {}, {} members, {} fitness cases",
+            self.seed,
+            self.population.len(),
+            self.dataset.rows()
+        );
     }
 
-    pub fn tick(&mut self) -> String {
-        utils::set_panic_hook();
-        let mut rng = rand::thread_rng();
-        self.gen += 1;
-
-        //termination sat for run?
-        //if best solution has a fitness less than 0.001, we can stop
-        if self.config.max_generations <= self.gen
-            || self.population.last().unwrap().fitness < 0.001
-            || self.done
-        {
-            log!("RUN COMPLETED ======================");
-            log!("Fitness Evaluations: {}", self.fitness_evaluations);
-            self.done = true;
-            let mut best_member_fitness = 0.0;
-            let mut best_member_string = String::new();
-            let mut best_member_json = String::new();
-            if let Some(member) = self.population.last() {
-                best_member_fitness = member.fitness;
-                best_member_string = self.chromosome_to_string(&member.chromosome);
-                best_member_json = self.chromosome_to_json(&member.chromosome);
-            }
-            return format!(
-                "{{\"done\": {}, \"fitness\":{} ,\"best\":\"{}\",\"gen\":\"{}\", \"chromosome\":{}}}",
-                true, best_member_fitness, best_member_string, self.gen, best_member_json
-            );
-        }
-
-        let mut new_population: Vec<Member> = Vec::new();
-        let mut i = 0;
-        while i < self.population.len() {
-            let rnum: f64 = rng.gen();
-            //10% chance of reproduction, 90% chance of crossover
-            //select one individual based on fitness
-            let individual1 = self.select_from_population(&self.population);
-            if rnum > 0.9 {
-                //insert copy in new pop
-                if self.insert_into_population(individual1, &mut new_population) {
-                    i += 1;
-                }
-            } else {
-                //select two individuals based on fitness
-                let individual2 = self.select_from_population(&self.population);
-                //perform crossover
-                let chromosome1 =
-                    self.crossover_function(&individual1.chromosome, &individual2.chromosome);
-                let child1 = Member {
-                    fitness: self.measure_fitness(&chromosome1),
-                    chromosome: chromosome1,
-                };
-                let chromosome2 =
-                    self.crossover_function(&individual1.chromosome, &individual2.chromosome);
-                let child2 = Member {
-                    fitness: self.measure_fitness(&chromosome2),
-                    chromosome: chromosome2,
-                };
+    /// Runs one generation and reports the best member so far, see `TickResult` for the fields.
+    /// `format` picks how `best` and `simplified` are printed, as for `format_expression`,
+    /// "infix" by default. Throws if `init` has not been called.
+    pub fn tick(&mut self, format: Option<String>) -> Result<JsValue, JsValue> {
+        let format = Format::from_name(format.as_deref().unwrap_or("infix"))
+            .map_err(|e| JsValue::from_str(&e))?;
+        let result = self.step().map_err(|e| JsValue::from_str(&e))?;
+        Ok(self.result_in(result, format).to_js())
+    }
 
-                let mut candidates: Vec<Member> = vec![individual1, individual2, child1, child2];
-                if self.config.fitness_order == "desc" {
-                    candidates.sort_by(|a, b| b.fitness.partial_cmp(&a.fitness).unwrap());
-                } else {
-                    candidates.sort_by(|a, b| a.fitness.partial_cmp(&b.fitness).unwrap());
-                }
-                if self.insert_into_population(candidates.pop().unwrap(), &mut new_population) {
-                    i += 1;
-                }
-                if self.insert_into_population(candidates.pop().unwrap(), &mut new_population) {
-                    i += 1;
-                }
-            }
-        }
-        //if new_population is shorter than population, then we hit a stop condition. The best solution
-        // may be in population, fill new population with the best of population so they are of the same size
-        while new_population.len() < self.population.len() {
-            new_population.push(self.population.remove(0))
-        }
-        self.population = new_population;
+    /// Statistics for every generation so far, oldest first, see `GenerationStats` for the fields.
+    pub fn history(&self) -> JsValue {
+        self.history
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .unwrap()
+    }
 
-        //sort population by fitness
-        if self.config.fitness_order == "desc" {
-            self.population
-                .sort_by(|a, b| b.fitness.partial_cmp(&a.fitness).unwrap());
-        } else {
-            self.population
-                .sort_by(|a, b| a.fitness.partial_cmp(&b.fitness).unwrap());
-        }
-
-        //return best so far
-        let mut best_member_fitness = 0.0;
-        let mut best_member_string = String::new();
-        let mut best_member_json = String::new();
-        if let Some(member) = self.population.last() {
-            best_member_fitness = member.fitness;
-            best_member_string = self.chromosome_to_string(&member.chromosome);
-            best_member_json = self.chromosome_to_json(&member.chromosome);
-        }
-        return format!(
-            "{{\"done\": {}, \"fitness\":{} ,\"best\":\"{}\",\"gen\":\"{}\", \"chromosome\":{}}}",
-            false, best_member_fitness, best_member_string, self.gen, best_member_json
-        );
+    /// The current population, best first, see `PopulationEntry` for the fields. `format` picks
+    /// how expressions are printed, as for `format_expression`, "infix" by default.
+    pub fn population(&self, format: Option<String>) -> Result<JsValue, JsValue> {
+        let format = Format::from_name(format.as_deref().unwrap_or("infix"))
+            .map_err(|e| JsValue::from_str(&e))?;
+        Ok(self
+            .population_in(format)
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .unwrap())
     }
 }
 
 impl GP {
-    fn generate_chromosome(&self, grow: bool) -> Node {
-        self.generate_chromosome_recursive(self.config.tree_limit_initial, grow)
+    /// Statistics for every generation so far, the Rust equivalent of `history`.
+    pub fn generation_stats(&self) -> &[GenerationStats] {
+        &self.history
     }
 
-    fn generate_chromosome_recursive(&self, limit: u16, grow: bool) -> Node {
-        let available_chromosomes = if grow {
-            &self.config.chromosome_function
-        } else if limit <= 1 {
-            &self.config.chromosome_terminal
-        } else {
-            &self.config.chromosome_combined
-        };
-        let max_action_index = available_chromosomes.len();
-        let mut rng = rand::thread_rng();
-        let random_number: f64 = rng.gen();
-        let action_index = (max_action_index as f64 * random_number).floor() as usize;
-        let action = available_chromosomes.get(action_index).unwrap();
-        if action == "R" {
-            let random_terminal: f64 = rng.gen();
-            Node {
-                action: Action::Terminal((random_terminal * 10.0).ceil()),
-                arg1: None,
-                arg2: None,
-            }
-        } else if action == "x" {
-            Node {
-                action: Action::X,
-                arg1: None,
-                arg2: None,
-            }
-        } else {
-            Node {
-                action: Action::Function(action.to_string()),
-                arg1: Some(Box::new(
-                    self.generate_chromosome_recursive(limit - 1, false),
-                )),
-                arg2: Some(Box::new(
-                    self.generate_chromosome_recursive(limit - 1, false),
-                )),
-            }
-        }
+    /// The Rust equivalent of `population`, with infix expressions. `render_expression` prints
+    /// them in other formats.
+    pub fn population_entries(&self) -> Vec<PopulationEntry> {
+        self.population_in(Format::Infix)
     }
 
-    fn insert_into_population(&self, member: Member, population: &mut Vec<Member>) -> bool {
-        population.push(member);
-        true
+    fn population_in(&self, format: Format) -> Vec<PopulationEntry> {
+        self.population
+            .iter()
+            .rev()
+            .map(|member| PopulationEntry {
+                fitness: Some(member.fitness).filter(|fitness| fitness.is_finite()),
+                expression: self.render_chromosome(&member.chromosome, format),
+                size: member.chromosome.len(),
+                depth: genome::depth(&member.chromosome),
+            })
+            .collect()
     }
 
-    fn measure_fitness(&mut self, chromosome: &Node) -> f64 {
-        self.fitness_evaluations += 1;
-        let mut fitness: f64 = 0.0;
-        let mut x: f64;
+    /// Runs one generation, the Rust equivalent of `tick`.
+    pub fn step(&mut self) -> Result<TickResult, String> {
+        self.check_started()?;
+        Ok(self.advance())
+    }
 
-        for x_y in self.fitness.as_slice() {
-            x = x_y[0];
-            let eval_res = self.eval_tree(chromosome, x);
-            let abs = (x_y[1] - eval_res).abs();
-            fitness += abs;
+    pub(crate) fn check_started(&self) -> Result<(), String> {
+        if self.population.is_empty() {
+            return Err("init must be called before running generations".to_string());
         }
-        if fitness.is_nan() {
-            if self.config.fitness_order == "desc" {
-                return 9999999.0;
-            } else {
-                return 0.0;
+        Ok(())
+    }
+
+    //`step` once the run has started
+    pub(crate) fn advance(&mut self) -> TickResult {
+        utils::set_panic_hook();
+        if self.paused() {
+            return self.result();
+        }
+
+        //termination sat for run? `gen` only counts generations actually bred
+        if self.stop_reason.is_none() {
+            self.stop_reason = self.check_termination();
+            if self.stop_reason.is_some() {
+                self.log_run_completed();
             }
         }
-        fitness
-    }
-
-    fn eval_tree(&self, chromosome: &Node, x: f64) -> f64 {
-        match &chromosome.action {
-            Action::Function(function_name) => {
-                if let Some(arg1) = &chromosome.arg1 {
-                    if let Some(arg2) = &chromosome.arg2 {
-                        return match function_name.as_str() {
-                            "+" => self.eval_tree(arg1, x) + self.eval_tree(arg2, x),
-                            "-" => self.eval_tree(arg1, x) - self.eval_tree(arg2, x),
-                            "*" => self.eval_tree(arg1, x) * self.eval_tree(arg2, x),
-                            "/" => self.eval_tree(arg1, x) / self.eval_tree(arg2, x),
-                            "sin" => (self.eval_tree(arg1, x)).sin(),
-                            "cos" => (self.eval_tree(arg1, x)).cos(),
-                            "exp" => (self.eval_tree(arg1, x)).powf(self.eval_tree(arg2, x)),
-                            _ => 9999999.0,
+        if self.stop_reason.is_some() {
+            return self.result();
+        }
+        self.gen += 1;
+
+        //the best members are carried over unchanged, so the best fitness can never get worse
+        let elite_count = (self.config.elite_count as usize).min(self.population.len());
+        let mut new_population: Vec<Member> =
+            self.population[self.population.len() - elite_count..].to_vec();
+        self.reset_population_keys(&new_population);
+        let mut mating_pool: Vec<usize> = Vec::new();
+        let mut i = new_population.len();
+        let size = self.population.len();
+        let mut cut_short = false;
+        while i < size {
+            //out of budget or time, stop mid-generation
+            let offspring = self.plan_offspring(size - i, &mut mating_pool);
+            if offspring.is_empty() {
+                cut_short = true;
+                break;
+            }
+            let chromosomes: Vec<&Genome> =
+                offspring.iter().flat_map(Offspring::chromosomes).collect();
+            let mut measurements = self.measure_fitness_batch(&chromosomes).into_iter();
+            for offspring in offspring {
+                match offspring {
+                    Offspring::Reproduction { parent, mutant } => {
+                        let member = match mutant {
+                            Some(chromosome) => {
+                                Member::measured(chromosome, measurements.next().unwrap())
+                            }
+                            None => self.population[parent].clone(),
                         };
+                        if self.insert_into_population(member, &mut new_population) {
+                            i += 1;
+                        }
+                    }
+                    Offspring::Crossover {
+                        parents,
+                        children,
+                        survivors,
+                    } => {
+                        let mut candidates: Vec<Candidate> = parents
+                            .iter()
+                            .map(|parent| Candidate::Parent(*parent))
+                            .collect();
+                        for chromosome in children {
+                            candidates.push(Candidate::Child(Member::measured(
+                                chromosome,
+                                measurements.next().unwrap(),
+                            )));
+                        }
+                        //the best of the parents and children go through, parents are only
+                        //cloned if they are among them
+                        let objective = self.metric.objective();
+                        let population = &self.population;
+                        candidates.sort_by(|a, b| {
+                            objective.compare(a.fitness(population), b.fitness(population))
+                        });
+                        for _ in 0..survivors {
+                            let member = match candidates.pop().unwrap() {
+                                Candidate::Parent(index) => self.population[index].clone(),
+                                Candidate::Child(member) => member,
+                            };
+                            if self.insert_into_population(member, &mut new_population) {
+                                i += 1;
+                            }
+                        }
                     }
                 }
-                9999999.0
             }
-            Action::Terminal(number) => *number,
-            Action::X => x,
         }
+        //if new_population is shorter than population, then we hit a stop condition. Fill it with the
+        // best of the previous population, skipping the elites which are already in it
+        let mut previous = std::mem::take(&mut self.population);
+        previous.truncate(size - elite_count);
+        while new_population.len() < size {
+            new_population.push(previous.pop().unwrap());
+        }
+        self.population = new_population;
+        if self
+            .config
+            .simplify_interval
+            .is_some_and(|interval| self.gen.is_multiple_of(interval))
+        {
+            self.simplify_population();
+        }
+        self.sort_population();
+        self.track_improvement();
+        self.update_hall_of_fame();
+        self.record_generation_stats();
+        //a generation cut short is the last one, report why right away
+        if cut_short {
+            self.stop_reason = self.check_termination();
+            if self.stop_reason.is_some() {
+                self.log_run_completed();
+            }
+        }
+
+        self.result()
     }
 
-    fn select_from_population(&self, population: &[Member]) -> Member {
-        let mut rng = rand::thread_rng();
-        let mut choices: Vec<&Member> = Vec::new();
-        let max_population_index = population.len();
-        for _ in 0..6 {
-            if let Some(member) =
-                population.get((max_population_index as f64 * rng.gen::<f64>()).floor() as usize)
-            {
-                choices.push(member);
-            }
+    fn result(&self) -> TickResult {
+        let best = self.population.last().unwrap();
+        TickResult {
+            done: self.stop_reason.is_some(),
+            gen: self.gen,
+            fitness: Some(best.fitness).filter(|fitness| fitness.is_finite()),
+            best: self.chromosome_to_string(&best.chromosome),
+            simplified: self.chromosome_to_string(&simplify::simplify(&best.chromosome)),
+            chromosome: self.chromosome_to_json(&best.chromosome),
+            fitness_evaluations: self.fitness_evaluations,
+            seed: self.seed.to_string(),
+            stop_reason: self.stop_reason.map(StopReason::as_str),
         }
-        let r: f64 = rng.gen();
+    }
 
-        if r < 0.5 {
-            return choices
-                .remove((choices.len() as f64 * rng.gen::<f64>()).floor() as usize)
-                .clone();
+    /// The Rust equivalent of `with_dataset`.
+    ///
+    /// An empty `Config::chromosome_terminal` is filled in with every dataset column plus `R`.
+    pub fn from_dataset(dataset: Dataset, mut config: Config) -> Result<GP, String> {
+        if config.chromosome_terminal.is_empty() {
+            config.set_chromosome_terminal(dataset.terminal_set());
         }
-        if self.config.fitness_order == "desc" {
-            choices.sort_by(|a, b| b.fitness.partial_cmp(&a.fitness).unwrap());
-        } else {
-            choices.sort_by(|a, b| a.fitness.partial_cmp(&b.fitness).unwrap());
+        config.validate()?;
+        for terminal in &config.chromosome_terminal {
+            if terminal != "R" && dataset.column_index(terminal).is_none() {
+                return Err(format!("terminal {} is not a dataset column", terminal));
+            }
         }
-        choices.pop().unwrap().clone()
+        for function_name in &config.chromosome_function {
+            if dataset.column_index(function_name).is_some() {
+                return Err(format!(
+                    "column {} has the same name as a function",
+                    function_name
+                ));
+            }
+        }
+        Ok(Self::from_config(dataset, config))
     }
+}
 
-    fn crossover_function(&self, parent1: &Node, parent2: &Node) -> Node {
-        let picked_node = self.pick_node(parent2);
-        let mut new_parent = parent1.clone();
-        self.swap_node(&mut new_parent, &picked_node);
+impl GP {
+    fn from_config(dataset: Dataset, config: Config) -> Self {
+        let seed = config
+            .seed
+            .unwrap_or_else(|| rand::thread_rng().gen::<u64>());
+
+        let functions = config
+            .chromosome_function
+            .iter()
+            .map(|name| {
+                Function::Builtin(Primitive::from_name(name).expect("config has been validated"))
+            })
+            .collect();
+        let terminals = config
+            .chromosome_terminal
+            .iter()
+            .map(|name| match dataset.column_index(name) {
+                Some(index) => Terminal::Var(index),
+                None => Terminal::Constant,
+            })
+            .collect();
+
+        let metric = FitnessMetric::from_config(&config).expect("config has been validated");
+        let dedup = Dedup::from_config(&config).expect("config has been validated");
+        let log_level = LogLevel::from_config(&config).expect("config has been validated");
+        let target_fitness = config
+            .target_fitness
+            .unwrap_or_else(|| metric.default_target(dataset.rows()));
 
-        if self.count_node_depth(&new_parent) > self.config.tree_limit_running {
-            return parent1.clone();
+        Self {
+            functions,
+            custom_primitives: Vec::new(),
+            terminals,
+            dataset,
+            selection: selection::from_config(&config).expect("config has been validated"),
+            metric,
+            target_fitness,
+            seed,
+            rng: Pcg32::seed_from_u64(seed),
+            config,
+            fitness_evaluations: 0,
+            gen: 0,
+            population: Vec::new(),
+            stop_reason: None,
+            started_at: 0.0,
+            paused_at: None,
+            best_fitness: 0.0,
+            last_improvement_gen: 0,
+            history: Vec::new(),
+            hall_of_fame: Vec::new(),
+            dedup,
+            population_keys: HashSet::new(),
+            consecutive_rejections: 0,
+            duplicates_rejected: 0,
+            logger: Logger::new(log_level),
+            seed_chromosomes: Vec::new(),
         }
-        new_parent
     }
 
-    fn pick_node(&self, node: &Node) -> Node {
-        let mut rng = rand::thread_rng();
-        let number_of_nodes = self.count_nodes(node);
-        let random_number: usize = (number_of_nodes as f64 * rng.gen::<f64>()).floor() as usize;
-        let (picked_node, _) = self.traverse_tree(node, random_number);
-        picked_node.unwrap_or_else(|| node.clone())
+    pub(crate) fn log_run_completed(&mut self) {
+        if let Some(stop_reason) = self.stop_reason {
+            log!(
+                self,
+                Info,
+                "run completed at gen {}: {}, {} fitness evaluations, best fitness {}",
+                self.gen,
+                stop_reason.as_str(),
+                self.fitness_evaluations,
+                self.best_fitness
+            );
+        }
     }
 
-    fn traverse_tree(&self, node: &Node, counter: usize) -> (Option<Node>, usize) {
-        if counter == 0 {
-            return (Some(node.clone()), 0);
-        }
-        if node.arg1.is_none() || node.arg2.is_none() {
-            return (None, counter);
+    fn remaining_fitness_evals(&self) -> usize {
+        (self.config.max_fitness_evals as usize).saturating_sub(self.fitness_evaluations)
+    }
+
+    fn out_of_time(&self) -> bool {
+        match self.config.max_time_ms {
+            Some(max_time_ms) => utils::now_ms() - self.started_at >= max_time_ms,
+            None => false,
         }
-        if let Some(arg1) = &node.arg1 {
-            let (arg1_node, counter) = self.traverse_tree(arg1, counter - 1);
-            if arg1_node.is_some() {
-                return (arg1_node, counter);
-            }
-            if let Some(arg2) = &node.arg2 {
-                self.traverse_tree(arg2, counter - 1)
-            } else {
-                (None, counter)
-            }
+    }
+
+    fn check_termination(&self) -> Option<StopReason> {
+        let best = self.population.last().unwrap().fitness;
+        if !self.metric.objective().is_better(self.target_fitness, best) {
+            Some(StopReason::TargetFitness)
+        } else if self.config.max_generations <= self.gen {
+            Some(StopReason::MaxGenerations)
+        } else if self.remaining_fitness_evals() == 0 {
+            Some(StopReason::MaxFitnessEvals)
+        } else if self.out_of_time() {
+            Some(StopReason::MaxTime)
+        } else if self
+            .config
+            .stagnation_limit
+            .is_some_and(|limit| self.gen - self.last_improvement_gen >= limit)
+        {
+            Some(StopReason::Stagnation)
         } else {
-            (None, counter)
+            None
+        }
+    }
+
+    //remember when the best fitness last got better, for the stagnation limit
+    fn track_improvement(&mut self) {
+        let best = self.population.last().unwrap().fitness;
+        if self.metric.objective().is_better(best, self.best_fitness) {
+            self.best_fitness = best;
+            self.last_improvement_gen = self.gen;
         }
     }
 
-    fn swap_node(&self, parent_node: &mut Node, new_node: &Node) {
-        let mut rng = rand::thread_rng();
-        let number_of_nodes = self.count_nodes(parent_node);
-        let random_number: usize = (number_of_nodes as f64 * rng.gen::<f64>()).floor() as usize;
-        self.update_tree(parent_node, new_node, random_number);
+    fn generate_chromosome(&mut self, grow: bool) -> Genome {
+        self.generate_chromosome_recursive(self.config.tree_limit_initial, grow)
     }
 
-    fn update_tree(&self, node: &mut Node, new_node: &Node, counter: usize) -> usize {
-        if counter == 0 {
-            node.action = new_node.action.clone();
-            node.arg1 = new_node.arg1.clone();
-            node.arg2 = new_node.arg2.clone();
-            return 0;
-        }
-        if node.arg1.is_none() || node.arg2.is_none() {
-            return counter;
+    fn generate_chromosome_recursive(&mut self, limit: u16, grow: bool) -> Genome {
+        let mut genes = Vec::new();
+        self.generate_genes(limit, grow, &mut genes);
+        genes
+    }
+
+    //append a random tree to `genes` in prefix order
+    fn generate_genes(&mut self, limit: u16, grow: bool, genes: &mut Genome) {
+        //the combined set is the functions followed by the terminals
+        let (offset, max_action_index) = if grow {
+            (0, self.functions.len())
+        } else if limit <= 1 {
+            (self.functions.len(), self.terminals.len())
+        } else {
+            (0, self.functions.len() + self.terminals.len())
+        };
+        let random_number: f64 = self.rng.gen();
+        let action_index = offset + (max_action_index as f64 * random_number).floor() as usize;
+        if action_index < self.functions.len() {
+            let function = self.functions[action_index];
+            let root = genes.len();
+            genes.push(Gene::leaf(Action::Function(function)));
+            for _ in 0..self.function_arity(function) {
+                self.generate_genes(limit - 1, false, genes);
+            }
+            genes[root].size = genes.len() - root;
+        } else {
+            let action = match self.terminals[action_index - self.functions.len()] {
+                Terminal::Constant => {
+                    let random_terminal: f64 = self.rng.gen();
+                    Action::Terminal((random_terminal * 10.0).ceil())
+                }
+                Terminal::Var(index) => Action::Var(index),
+            };
+            genes.push(Gene::leaf(action));
         }
-        if let Some(ref mut arg1) = node.arg1 {
-            let counter = self.update_tree(arg1.as_mut(), new_node, counter - 1);
-            if counter == 0 {
-                return 0;
+    }
+
+    //plan enough offspring to fill `slots` places in the new population, drawing everything
+    //random up front so the whole batch can be measured at once. Stops early when the budget
+    //or time runs out
+    fn plan_offspring(&mut self, slots: usize, mating_pool: &mut Vec<usize>) -> Vec<Offspring> {
+        let mut offspring = Vec::new();
+        let mut filled = 0;
+        let mut evaluations = 0;
+        while filled < slots {
+            let remaining = self.remaining_fitness_evals() - evaluations;
+            if remaining == 0 || self.out_of_time() {
+                break;
             }
-            if let Some(ref mut arg2) = node.arg2 {
-                self.update_tree(arg2.as_mut(), new_node, counter - 1)
+            let rnum: f64 = self.rng.gen();
+            //10% chance of reproduction, 90% chance of crossover
+            //select one individual based on fitness
+            let parent1 = self.select_from_population(mating_pool);
+            if rnum > 0.9 {
+                //a copy of the parent, re-measured if mutation changed it
+                let mut chromosome = self.population[parent1].chromosome.clone();
+                let mutant = if self.mutate(&mut chromosome) {
+                    evaluations += 1;
+                    Some(chromosome)
+                } else {
+                    None
+                };
+                offspring.push(Offspring::Reproduction {
+                    parent: parent1,
+                    mutant,
+                });
+                filled += 1;
             } else {
-                counter
+                //select two individuals based on fitness
+                let parent2 = self.select_from_population(mating_pool);
+                //perform crossover
+                let mut children = vec![self.crossover_function(parent1, parent2)];
+                self.mutate(&mut children[0]);
+                let survivors = (slots - filled).min(2);
+                //only make the second child if there is room and the budget can pay for it
+                if survivors == 2 && remaining > 1 {
+                    let mut chromosome2 = self.crossover_function(parent1, parent2);
+                    self.mutate(&mut chromosome2);
+                    children.push(chromosome2);
+                }
+                evaluations += children.len();
+                offspring.push(Offspring::Crossover {
+                    parents: [parent1, parent2],
+                    children,
+                    survivors,
+                });
+                filled += survivors;
             }
-        } else {
-            counter
         }
+        offspring
     }
 
-    fn count_nodes(&self, node: &Node) -> usize {
-        if let Some(arg1) = &node.arg1 {
-            if let Some(arg2) = &node.arg2 {
-                return self.count_nodes(arg1) + self.count_nodes(arg2) + 1;
+    //measure every chromosome, in parallel when the parallel feature allows it
+    fn measure_fitness_batch(&mut self, chromosomes: &[&Genome]) -> Vec<Measurement> {
+        self.fitness_evaluations += chromosomes.len();
+        #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+        {
+            if let Some(measurements) = self.measure_fitness_parallel(chromosomes) {
+                return measurements;
             }
         }
-        1
+        chromosomes
+            .iter()
+            .map(|chromosome| self.measurement(&self.predict(chromosome)))
+            .collect()
     }
 
-    fn count_node_depth(&self, node: &Node) -> u16 {
-        if let Some(arg1) = &node.arg1 {
-            if let Some(arg2) = &node.arg2 {
-                return cmp::max(self.count_node_depth(arg1), self.count_node_depth(arg2)) + 1;
-            }
+    //fitness and dedup signature from a chromosome's outputs
+    fn measurement(&self, outputs: &[f64]) -> Measurement {
+        Measurement {
+            fitness: self.metric.fitness(outputs, self.dataset.targets()),
+            signature: self.dedup.signature(outputs),
         }
-        1
-    }
-
-    fn chromosome_to_string(&self, chromosome: &Node) -> String {
-        match &chromosome.action {
-            Action::Function(function_name) => {
-                if let Some(arg1) = &chromosome.arg1 {
-                    if let Some(arg2) = &chromosome.arg2 {
-                        return match function_name.as_str() {
-                            "+" => format!(
-                                "({} + {})",
-                                self.chromosome_to_string(arg1),
-                                self.chromosome_to_string(arg2)
-                            ),
-                            "-" => format!(
-                                "({} - {})",
-                                self.chromosome_to_string(arg1),
-                                self.chromosome_to_string(arg2)
-                            ),
-                            "*" => format!(
-                                "({} * {})",
-                                self.chromosome_to_string(arg1),
-                                self.chromosome_to_string(arg2)
-                            ),
-                            "/" => format!(
-                                "({} / {})",
-                                self.chromosome_to_string(arg1),
-                                self.chromosome_to_string(arg2)
-                            ),
-                            "sin" => format!("sin({})", self.chromosome_to_string(arg1)),
-
-                            "cos" => format!("cos({})", self.chromosome_to_string(arg1)),
-
-                            "exp" => format!(
-                                "exp({}, {})",
-                                self.chromosome_to_string(arg1),
-                                self.chromosome_to_string(arg2)
-                            ),
-
-                            _ => "".to_string(),
-                        };
-                    }
-                }
-                "(error)".to_string()
+    }
+
+    //the chromosome's output for every fitness case
+    fn predict(&self, chromosome: &[Gene]) -> Vec<f64> {
+        self.run_program(&Program::compile(chromosome))
+    }
+
+    //take the next parent from the mating pool, refilling it with the configured selection when empty
+    fn select_from_population(&mut self, mating_pool: &mut Vec<usize>) -> usize {
+        if mating_pool.is_empty() {
+            let fitness: Vec<f64> = self.population.iter().map(|m| m.fitness).collect();
+            *mating_pool =
+                self.selection
+                    .select_many(&fitness, self.population.len(), &mut self.rng);
+        }
+        mating_pool.pop().unwrap()
+    }
+
+    //sort population by fitness, so the best member is last
+    fn sort_population(&mut self) {
+        let objective = self.metric.objective();
+        self.population
+            .sort_by(|a, b| objective.compare(a.fitness, b.fitness));
+    }
+
+    //copy parent1 with a random subtree replaced by a random subtree of parent2
+    fn crossover_function(&mut self, parent1: usize, parent2: usize) -> Genome {
+        let picked = self.random_node_index(self.population[parent2].chromosome.len());
+        let point = self.random_node_index(self.population[parent1].chromosome.len());
+        let (parent1, parent2) = (
+            &self.population[parent1].chromosome,
+            &self.population[parent2].chromosome,
+        );
+        let child = genome::replace_subtree(parent1, point, genome::subtree(parent2, picked));
+
+        if genome::depth(&child) > self.config.tree_limit_running {
+            return parent1.clone();
+        }
+        child
+    }
+
+    fn chromosome_to_string(&self, chromosome: &[Gene]) -> String {
+        match &chromosome[0].action {
+            Action::Function(function) => {
+                let args: Vec<String> = genome::args(chromosome)
+                    .map(|arg| self.chromosome_to_string(arg))
+                    .collect();
+                self.format_function(*function, &args)
             }
             Action::Terminal(number) => number.to_string(),
-            Action::X => "x".to_string(),
-        }
-    }
-
-    fn chromosome_to_json(&self, chromosome: &Node) -> String {
-        match &chromosome.action {
-            Action::Function(function_name) => {
-                if let Some(arg1) = &chromosome.arg1 {
-                    if let Some(arg2) = &chromosome.arg2 {
-                        return match function_name.as_str() {
-                            "+" => format!(
-                                "{{\"action\":\"+\",\"arg1\":{},\"arg2\":{}}}",
-                                self.chromosome_to_json(arg1),
-                                self.chromosome_to_json(arg2)
-                            ),
-                            "-" => format!(
-                                "{{\"action\":\"-\",\"arg1\":{},\"arg2\":{}}}",
-                                self.chromosome_to_json(arg1),
-                                self.chromosome_to_json(arg2)
-                            ),
-                            "*" => format!(
-                                "{{\"action\":\"*\",\"arg1\":{},\"arg2\":{}}}",
-                                self.chromosome_to_json(arg1),
-                                self.chromosome_to_json(arg2)
-                            ),
-                            "/" => format!(
-                                "{{\"action\":\"/\",\"arg1\":{},\"arg2\":{}}}",
-                                self.chromosome_to_json(arg1),
-                                self.chromosome_to_json(arg2)
-                            ),
-                            "sin" => format!(
-                                "{{\"action\":\"sin\",\"arg1\":{}}}",
-                                self.chromosome_to_json(arg1)
-                            ),
-
-                            "cos" => format!(
-                                "{{\"action\":\"cos\",\"arg1\":{}}}",
-                                self.chromosome_to_json(arg1)
-                            ),
-
-                            "exp" => format!(
-                                "{{\"action\":\"exp\",\"arg1\":{},\"arg2\":{}}}",
-                                self.chromosome_to_json(arg1),
-                                self.chromosome_to_json(arg2)
-                            ),
-
-                            _ => "".to_string(),
-                        };
-                    }
+            Action::Var(index) => self.dataset.column_name(*index).to_string(),
+        }
+    }
+
+    //the tree shape sent to JS: {"action": "+", "arg1": {...}, "arg2": {...}}, with constants as
+    //numeric actions, or "NaN", "inf" and "-inf", which JSON has no numbers for
+    fn chromosome_to_json(&self, chromosome: &[Gene]) -> serde_json::Value {
+        match &chromosome[0].action {
+            Action::Function(function) => {
+                let mut json = json!({ "action": self.function_name(*function) });
+                for (index, arg) in genome::args(chromosome).enumerate() {
+                    json[format!("arg{}", index + 1)] = self.chromosome_to_json(arg);
                 }
-                "(error)".to_string()
+                json
             }
-            Action::Terminal(number) => format!("{{\"action\":{}}}", number),
-            Action::X => "{\"action\":\"x\"}".to_string(),
+            Action::Terminal(number) if number.is_finite() => json!({ "action": number }),
+            Action::Terminal(number) => json!({ "action": number.to_string() }),
+            Action::Var(index) => json!({ "action": self.dataset.column_name(*index) }),
         }
     }
 }
@@ -0,0 +1,127 @@
+//! Run events, written to the browser console on wasm, to stderr natively, or to a handler set
+//! with `GP::set_log_callback` or `GP::set_log_handler`.
+
+use crate::{Config, GP};
+use wasm_bindgen::prelude::*;
+
+/// How much a run reports, chosen by `Config::log_level`. Each level includes the ones above it.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    /// The start and end of the run.
+    Info,
+    /// A summary of every generation.
+    Debug,
+}
+
+impl LogLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+        }
+    }
+
+    /// The level set by `Config::log_level`, `None` when logging is off.
+    pub(crate) fn from_config(config: &Config) -> Result<Option<LogLevel>, String> {
+        match config.log_level.as_str() {
+            "off" => Ok(None),
+            "error" => Ok(Some(LogLevel::Error)),
+            "warn" => Ok(Some(LogLevel::Warn)),
+            "info" => Ok(Some(LogLevel::Info)),
+            "debug" => Ok(Some(LogLevel::Debug)),
+            _ => Err(format!("unknown log_level: {}", config.log_level)),
+        }
+    }
+}
+
+type Handler = Box<dyn FnMut(LogLevel, &str)>;
+
+pub(crate) struct Logger {
+    level: Option<LogLevel>,
+    //replaces the default output when set
+    handler: Option<Handler>,
+}
+
+impl Logger {
+    pub(crate) fn new(level: Option<LogLevel>) -> Logger {
+        Logger {
+            level,
+            handler: None,
+        }
+    }
+
+    pub(crate) fn enabled(&self, level: LogLevel) -> bool {
+        self.level.is_some_and(|max| level <= max)
+    }
+
+    pub(crate) fn write(&mut self, level: LogLevel, message: &str) {
+        match &mut self.handler {
+            Some(handler) => handler(level, message),
+            None => write_default(level, message),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_default(level: LogLevel, message: &str) {
+    let message = JsValue::from_str(message);
+    match level {
+        LogLevel::Error => web_sys::console::error_1(&message),
+        LogLevel::Warn => web_sys::console::warn_1(&message),
+        LogLevel::Info => web_sys::console::info_1(&message),
+        LogLevel::Debug => web_sys::console::debug_1(&message),
+    }
+}
+
+//there is no console outside the browser
+#[cfg(not(target_arch = "wasm32"))]
+fn write_default(level: LogLevel, message: &str) {
+    eprintln!("[{}] {}", level.as_str(), message);
+}
+
+#[wasm_bindgen]
+impl GP {
+    /// Sends log messages to `callback`, called with the level ("error", "warn", "info" or
+    /// "debug") and the message, instead of the console. `null` restores console output.
+    /// Messages above `Config::log_level` are not sent.
+    pub fn set_log_callback(&mut self, callback: Option<js_sys::Function>) {
+        self.logger.handler = callback.map(|callback| {
+            Box::new(move |level: LogLevel, message: &str| {
+                //a throwing callback must not stop the run
+                let _ = callback.call2(
+                    &JsValue::NULL,
+                    &JsValue::from_str(level.as_str()),
+                    &JsValue::from_str(message),
+                );
+            }) as Handler
+        });
+    }
+}
+
+impl GP {
+    /// The Rust equivalent of `set_log_callback`.
+    pub fn set_log_handler(&mut self, handler: impl FnMut(LogLevel, &str) + 'static) {
+        self.logger.handler = Some(Box::new(handler));
+    }
+
+    /// Changes `Config::log_level` of an existing run, such as one continued with
+    /// `restore_state`, which brings back the saved level.
+    pub fn set_log_level(&mut self, log_level: &str) -> Result<(), String> {
+        let previous = std::mem::replace(&mut self.config.log_level, log_level.to_string());
+        match LogLevel::from_config(&self.config) {
+            Ok(level) => {
+                self.logger.level = level;
+                Ok(())
+            }
+            Err(e) => {
+                self.config.log_level = previous;
+                Err(e)
+            }
+        }
+    }
+}
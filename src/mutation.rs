@@ -0,0 +1,242 @@
+use crate::genome::{self, Gene, Genome};
+use crate::primitive::Function;
+use crate::{Action, GP};
+use rand::prelude::*;
+
+impl GP {
+    /// Applies each mutation operator to the chromosome with its configured probability.
+    /// Returns true if the chromosome was changed.
+    pub(crate) fn mutate(&mut self, chromosome: &mut Genome) -> bool {
+        let mut mutated = false;
+        if self.rng.gen::<f32>() < self.config.mutate_prob {
+            mutated |= self.subtree_mutation(chromosome);
+        }
+        if self.rng.gen::<f32>() < self.config.point_mutate_prob {
+            mutated |= self.point_mutation(chromosome);
+        }
+        if self.rng.gen::<f32>() < self.config.hoist_mutate_prob {
+            mutated |= self.hoist_mutation(chromosome);
+        }
+        if self.rng.gen::<f32>() < self.config.shrink_mutate_prob {
+            mutated |= self.shrink_mutation(chromosome);
+        }
+        mutated
+    }
+
+    //replace a random subtree with a freshly generated one
+    fn subtree_mutation(&mut self, chromosome: &mut Genome) -> bool {
+        let new_subtree = self.generate_chromosome_recursive(self.config.tree_limit_initial, false);
+        let index = self.random_node_index(chromosome.len());
+        let mutant = genome::replace_subtree(chromosome, index, &new_subtree);
+
+        if genome::depth(&mutant) > self.config.tree_limit_running {
+            return false;
+        }
+        *chromosome = mutant;
+        true
+    }
+
+    //swap a function for another of the same arity, or nudge a constant
+    fn point_mutation(&mut self, chromosome: &mut Genome) -> bool {
+        let index = self.random_node_index(chromosome.len());
+        match chromosome[index].action {
+            Action::Function(function) => {
+                let arity = self.function_arity(function);
+                let candidates: Vec<Function> = self
+                    .functions
+                    .iter()
+                    .cloned()
+                    .filter(|other| *other != function && self.function_arity(*other) == arity)
+                    .collect();
+                if candidates.is_empty() {
+                    return false;
+                }
+                let picked =
+                    candidates[(candidates.len() as f64 * self.rng.gen::<f64>()).floor() as usize];
+                chromosome[index].action = Action::Function(picked);
+            }
+            Action::Terminal(number) => {
+                chromosome[index].action = Action::Terminal(number + self.rng.gen_range(-1.0, 1.0));
+            }
+            Action::Var(_) => return false,
+        }
+        true
+    }
+
+    //replace the whole tree with one of its own subtrees
+    fn hoist_mutation(&mut self, chromosome: &mut Genome) -> bool {
+        let number_of_nodes = chromosome.len();
+        if number_of_nodes < 2 {
+            return false;
+        }
+        //skip index 0 so the hoisted subtree is never the root itself
+        let index = 1 + ((number_of_nodes - 1) as f64 * self.rng.gen::<f64>()).floor() as usize;
+        *chromosome = genome::subtree(chromosome, index).to_vec();
+        true
+    }
+
+    //replace a random function node with a terminal
+    fn shrink_mutation(&mut self, chromosome: &mut Genome) -> bool {
+        //every function has at least one argument, so only functions root more than one gene
+        let function_indexes: Vec<usize> = chromosome
+            .iter()
+            .enumerate()
+            .filter(|(_, gene)| gene.size > 1)
+            .map(|(index, _)| index)
+            .collect();
+        if function_indexes.is_empty() {
+            return false;
+        }
+        let index = function_indexes
+            [(function_indexes.len() as f64 * self.rng.gen::<f64>()).floor() as usize];
+        let terminal: Vec<Gene> = self.generate_chromosome_recursive(1, false);
+        *chromosome = genome::replace_subtree(chromosome, index, &terminal);
+        true
+    }
+
+    pub(crate) fn random_node_index(&mut self, number_of_nodes: usize) -> usize {
+        (number_of_nodes as f64 * self.rng.gen::<f64>()).floor() as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::genome::{self, Genome};
+    use crate::{Action, Config, Dataset, GP};
+
+    fn new_gp(configure: impl FnOnce(&mut Config)) -> GP {
+        let mut config = Config::new();
+        config.set_seed(Some(7));
+        config.set_chromosome_function(
+            ["+", "-", "*", "/", "sin", "cos"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+        );
+        configure(&mut config);
+        GP::from_dataset(Dataset::from_pairs(&[1.0, 2.0, 3.0, 4.0]), config).unwrap()
+    }
+
+    fn chromosome(gp: &GP) -> Genome {
+        gp.parse_chromosome("sin(x * 2) + cos(x) / (3 - x)")
+            .unwrap()
+    }
+
+    fn actions(genes: &[genome::Gene]) -> Vec<Action> {
+        genes.iter().map(|gene| gene.action).collect()
+    }
+
+    #[test]
+    fn point_mutation_keeps_the_shape() {
+        let mut gp = new_gp(|_| {});
+        let original = chromosome(&gp);
+        for _ in 0..200 {
+            let mut mutant = original.clone();
+            gp.point_mutation(&mut mutant);
+            assert_eq!(mutant.len(), original.len());
+            for (gene, before) in mutant.iter().zip(&original) {
+                assert_eq!(gene.size, before.size);
+                match (gene.action, before.action) {
+                    (Action::Function(function), Action::Function(previous)) => {
+                        assert_eq!(gp.function_arity(function), gp.function_arity(previous))
+                    }
+                    (Action::Terminal(_), Action::Terminal(_)) => {}
+                    (action, previous) => assert_eq!(action, previous),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hoist_mutation_keeps_a_subtree() {
+        let mut gp = new_gp(|_| {});
+        let original = chromosome(&gp);
+        for _ in 0..200 {
+            let mut mutant = original.clone();
+            assert!(gp.hoist_mutation(&mut mutant));
+            assert!(mutant.len() < original.len());
+            assert!((1..original.len()).any(|index| genome::subtree(&original, index) == mutant));
+        }
+    }
+
+    #[test]
+    fn shrink_mutation_replaces_a_function_with_a_terminal() {
+        let mut gp = new_gp(|_| {});
+        let original = chromosome(&gp);
+        for _ in 0..200 {
+            let mut mutant = original.clone();
+            assert!(gp.shrink_mutation(&mut mutant));
+            //the first gene that differs roots the replaced subtree
+            let index = (0..mutant.len())
+                .find(|index| mutant[*index].action != original[*index].action)
+                .unwrap();
+            assert!(original[index].size > 1);
+            assert_eq!(mutant[index].size, 1);
+            assert!(!matches!(mutant[index].action, Action::Function(_)));
+            let end = index + original[index].size;
+            assert_eq!(actions(&mutant[index + 1..]), actions(&original[end..]));
+        }
+    }
+
+    #[test]
+    fn mutation_probabilities_are_honoured() {
+        let mut gp = new_gp(|config| {
+            config.set_mutate_prob(0.0);
+            config.set_point_mutate_prob(0.0);
+            config.set_hoist_mutate_prob(0.0);
+            config.set_shrink_mutate_prob(0.0);
+        });
+        let original = chromosome(&gp);
+        for _ in 0..100 {
+            let mut mutant = original.clone();
+            assert!(!gp.mutate(&mut mutant));
+            assert_eq!(mutant, original);
+        }
+
+        let only = |operator: usize| {
+            new_gp(|config| {
+                config.set_mutate_prob(if operator == 0 { 1.0 } else { 0.0 });
+                config.set_point_mutate_prob(if operator == 1 { 1.0 } else { 0.0 });
+                config.set_hoist_mutate_prob(if operator == 2 { 1.0 } else { 0.0 });
+                config.set_shrink_mutate_prob(if operator == 3 { 1.0 } else { 0.0 });
+            })
+        };
+        //only functions and constants, so point mutation always finds something to change
+        let mut gp = only(1);
+        let original = gp.parse_chromosome("sin(2 * 3) + cos(4) / 5").unwrap();
+        for _ in 0..100 {
+            let mut mutant = original.clone();
+            assert!(gp.mutate(&mut mutant));
+            assert_ne!(mutant, original);
+        }
+        for operator in [0, 2, 3] {
+            let mut gp = only(operator);
+            let original = chromosome(&gp);
+            for _ in 0..100 {
+                let mut mutant = original.clone();
+                assert!(gp.mutate(&mut mutant), "operator {}", operator);
+                if operator != 0 {
+                    assert_ne!(mutant, original);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn subtree_mutation_stays_within_the_depth_limit() {
+        let mut gp = new_gp(|config| {
+            config.set_tree_limit_initial(4);
+            config.set_tree_limit_running(5);
+        });
+        let mut chromosome = chromosome(&gp);
+        assert_eq!(genome::depth(&chromosome), 4);
+        let mut mutated = 0;
+        for _ in 0..500 {
+            if gp.subtree_mutation(&mut chromosome) {
+                mutated += 1;
+            }
+            assert!(genome::depth(&chromosome) <= 5);
+        }
+        assert!(mutated > 0);
+    }
+}
@@ -0,0 +1,49 @@
+//! Fitness evaluation across threads, behind the `parallel` feature on native targets.
+
+use crate::genome::Genome;
+use crate::primitive::{Function, Kernel, Primitive};
+use crate::program::Program;
+use crate::{Measurement, GP};
+use rayon::prelude::*;
+
+impl GP {
+    /// Measures the chromosomes on rayon's thread pool. Fitness only depends on the chromosome,
+    /// so the results are the same as measuring them one after another. Returns `None` when a
+    /// JS primitive is registered, since JS functions cannot be called from other threads.
+    pub(crate) fn measure_fitness_parallel(
+        &self,
+        chromosomes: &[&Genome],
+    ) -> Option<Vec<Measurement>> {
+        let kernels: Vec<Primitive> = self
+            .custom_primitives
+            .iter()
+            .map(|primitive| match primitive.kernel {
+                Kernel::Builtin(primitive) => Some(primitive),
+                Kernel::Js(_) => None,
+            })
+            .collect::<Option<_>>()?;
+        let (dataset, metric, dedup) = (&self.dataset, self.metric, self.dedup);
+        Some(
+            chromosomes
+                .par_iter()
+                .map(|chromosome| {
+                    let predictions =
+                        Program::compile(chromosome).run(dataset, |function, first, rest| {
+                            match function {
+                                Function::Builtin(primitive) => {
+                                    primitive.apply_columns(first, rest)
+                                }
+                                Function::Custom(index) => {
+                                    kernels[index].apply_columns(first, rest)
+                                }
+                            }
+                        });
+                    Measurement {
+                        fitness: metric.fitness(&predictions, dataset.targets()),
+                        signature: dedup.signature(&predictions),
+                    }
+                })
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,363 @@
+//! Reads chromosomes back from text: the infix printed by `chromosome_to_string` and the JSON
+//! tree built by `chromosome_to_json`.
+//!
+//! The infix grammar is wider than the printer's output. Parentheses around `+ - * /` may be
+//! left out, in which case `*` and `/` bind tighter than `+` and `-`, and a leading `-` negates.
+//! Functions are written `name(arg, ...)`, also for registered primitives printed through a
+//! template. Every function must be in the run's function set and every variable a dataset
+//! column.
+
+use crate::genome::{self, Gene, Genome};
+use crate::primitive::{Function, Primitive};
+use crate::{Action, PopulationEntry, GP};
+use serde::Serialize;
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+impl GP {
+    /// Adds an expression, in the infix or JSON form `tick` reports, to the initial population
+    /// built by `init`, ahead of the random members. Must be called before `init`, and the tree
+    /// may not be deeper than `Config::tree_limit_running`.
+    pub fn seed_expression(&mut self, expression: &str) -> Result<(), JsValue> {
+        self.add_seed_expression(expression)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Measures an expression, in the infix or JSON form `tick` reports, on this run's fitness
+    /// cases without touching the run. Returns `{fitness, expression, size, depth}` like the
+    /// entries of `population`.
+    pub fn evaluate_expression(&self, expression: &str) -> Result<JsValue, JsValue> {
+        let entry = self
+            .measure_expression(expression)
+            .map_err(|e| JsValue::from_str(&e))?;
+        Ok(serde_wasm_bindgen::to_value(&entry)?)
+    }
+
+    /// The JSON tree of an expression given in infix, as in the `chromosome` field of `tick`.
+    pub fn expression_to_json(&self, expression: &str) -> Result<JsValue, JsValue> {
+        let json = self
+            .expression_json(expression)
+            .map_err(|e| JsValue::from_str(&e))?;
+        Ok(json.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+    }
+}
+
+impl GP {
+    /// The Rust equivalent of `seed_expression`.
+    pub fn add_seed_expression(&mut self, expression: &str) -> Result<(), String> {
+        if !self.population.is_empty() {
+            return Err("seed expressions must be added before init".to_string());
+        }
+        let chromosome = self.parse_chromosome(expression)?;
+        let depth = genome::depth(&chromosome);
+        if depth > self.config.tree_limit_running {
+            return Err(format!(
+                "the seed is {} deep, more than tree_limit_running ({})",
+                depth, self.config.tree_limit_running
+            ));
+        }
+        self.seed_chromosomes.push(chromosome);
+        Ok(())
+    }
+
+    /// The Rust equivalent of `evaluate_expression`. Does not count as a fitness evaluation.
+    pub fn measure_expression(&self, expression: &str) -> Result<PopulationEntry, String> {
+        let chromosome = self.parse_chromosome(expression)?;
+        let fitness = self
+            .metric
+            .fitness(&self.predict(&chromosome), self.dataset.targets());
+        Ok(PopulationEntry {
+            fitness: Some(fitness).filter(|fitness| fitness.is_finite()),
+            expression: self.chromosome_to_string(&chromosome),
+            size: chromosome.len(),
+            depth: genome::depth(&chromosome),
+        })
+    }
+
+    /// The Rust equivalent of `expression_to_json`.
+    pub fn expression_json(&self, expression: &str) -> Result<Value, String> {
+        Ok(self.chromosome_to_json(&self.parse_chromosome(expression)?))
+    }
+
+    //infix, or JSON when the text is an object
+    pub(crate) fn parse_chromosome(&self, text: &str) -> Result<Genome, String> {
+        if text.trim_start().starts_with('{') {
+            let json: Value =
+                serde_json::from_str(text).map_err(|e| format!("invalid JSON: {}", e))?;
+            return self.parse_json(&json);
+        }
+        let mut parser = Parser {
+            gp: self,
+            text,
+            position: 0,
+            depth: 0,
+        };
+        let chromosome = parser.expression()?;
+        parser.skip_whitespace();
+        if parser.position < text.len() {
+            return Err(parser.error("unexpected input"));
+        }
+        Ok(chromosome)
+    }
+
+    fn parse_json(&self, json: &Value) -> Result<Genome, String> {
+        let action = json
+            .get("action")
+            .ok_or_else(|| format!("expected an object with an action, got {}", json))?;
+        match action {
+            Value::Number(number) => Ok(constant(number.as_f64().unwrap_or(f64::NAN))),
+            Value::String(name) => {
+                if let Some(index) = self.dataset.column_index(name) {
+                    return Ok(vec![Gene::leaf(Action::Var(index))]);
+                }
+                //the constants JSON has no numbers for
+                match name.as_str() {
+                    "NaN" => return Ok(constant(f64::NAN)),
+                    "inf" => return Ok(constant(f64::INFINITY)),
+                    "-inf" => return Ok(constant(f64::NEG_INFINITY)),
+                    _ => {}
+                }
+                let function = self.find_function(name)?;
+                let args = (1..=self.function_arity(function))
+                    .map(|index| {
+                        let arg = json
+                            .get(format!("arg{}", index))
+                            .ok_or_else(|| format!("{} is missing arg{}", name, index))?;
+                        self.parse_json(arg)
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(genome::node(Action::Function(function), args))
+            }
+            _ => Err(format!("invalid action: {}", action)),
+        }
+    }
+
+    fn find_function(&self, name: &str) -> Result<Function, String> {
+        self.functions
+            .iter()
+            .cloned()
+            .find(|function| self.function_name(*function) == name)
+            .ok_or_else(|| {
+                if Primitive::from_name(name).is_some() {
+                    format!("{} is not in the function set", name)
+                } else {
+                    format!("unknown name: {}", name)
+                }
+            })
+    }
+}
+
+fn constant(value: f64) -> Genome {
+    vec![Gene::leaf(Action::Terminal(value))]
+}
+
+//how deep parentheses, arguments and negations may nest before parsing would risk the stack
+const MAX_NESTING: usize = 256;
+
+struct Parser<'a> {
+    gp: &'a GP,
+    text: &'a str,
+    //byte offset of the next character
+    position: usize,
+    //the number of `unary` calls in progress
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    //sum := product (("+" | "-") product)*
+    fn expression(&mut self) -> Result<Genome, String> {
+        let mut left = self.product()?;
+        while let Some((operator, start)) = self.operator(&['+', '-']) {
+            let right = self.product()?;
+            left = self.binary(operator, start, left, right)?;
+        }
+        Ok(left)
+    }
+
+    //product := unary (("*" | "/") unary)*
+    fn product(&mut self) -> Result<Genome, String> {
+        let mut left = self.unary()?;
+        while let Some((operator, start)) = self.operator(&['*', '/']) {
+            let right = self.unary()?;
+            left = self.binary(operator, start, left, right)?;
+        }
+        Ok(left)
+    }
+
+    //unary := "-" unary | primary
+    fn unary(&mut self) -> Result<Genome, String> {
+        self.skip_whitespace();
+        //every level of nesting passes through here
+        if self.depth == MAX_NESTING {
+            return Err(self.error("expression nested too deeply"));
+        }
+        self.depth += 1;
+        let unary = self.negation();
+        self.depth -= 1;
+        unary
+    }
+
+    fn negation(&mut self) -> Result<Genome, String> {
+        if !self.eat('-') {
+            return self.primary();
+        }
+        let start = self.position - 1;
+        let operand = self.unary()?;
+        match operand[0].action {
+            Action::Terminal(value) => Ok(constant(-value)),
+            _ => {
+                let function = self.function("-", start)?;
+                Ok(genome::node(
+                    Action::Function(function),
+                    vec![constant(0.0), operand],
+                ))
+            }
+        }
+    }
+
+    //primary := number | name | name "(" sum ("," sum)* ")" | "(" sum ")"
+    fn primary(&mut self) -> Result<Genome, String> {
+        self.skip_whitespace();
+        let start = self.position;
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let inner = self.expression()?;
+                self.expect(')')?;
+                Ok(inner)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                self.take_while(|c| c.is_ascii_digit() || c == '.');
+                //an exponent, as in 1e-7
+                let rest = &self.text[self.position..];
+                if rest.starts_with(['e', 'E']) {
+                    let sign = usize::from(rest[1..].starts_with(['+', '-']));
+                    if rest[1 + sign..].starts_with(|c: char| c.is_ascii_digit()) {
+                        self.position += 1 + sign;
+                        self.take_while(|c| c.is_ascii_digit());
+                    }
+                }
+                let number = &self.text[start..self.position];
+                number
+                    .parse()
+                    .map(constant)
+                    .map_err(|_| self.error_at(start, &format!("invalid number {}", number)))
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let name = self.take_while(|c| c.is_alphanumeric() || c == '_');
+                self.skip_whitespace();
+                if self.peek() == Some('(') {
+                    self.position += 1;
+                    return self.call(name, start);
+                }
+                if let Some(index) = self.gp.dataset.column_index(name) {
+                    return Ok(vec![Gene::leaf(Action::Var(index))]);
+                }
+                //as printed for NaN and infinite constants
+                match name {
+                    "NaN" => Ok(constant(f64::NAN)),
+                    "inf" => Ok(constant(f64::INFINITY)),
+                    _ => Err(self.error_at(start, &format!("unknown variable {}", name))),
+                }
+            }
+            Some(_) => Err(self.error("expected a number, variable or function")),
+            None => Err(self.error("unexpected end of expression")),
+        }
+    }
+
+    //the arguments of `name`, after its opening parenthesis
+    fn call(&mut self, name: &str, start: usize) -> Result<Genome, String> {
+        let function = self.function(name, start)?;
+        let mut args = vec![self.expression()?];
+        while self.operator(&[',']).is_some() {
+            args.push(self.expression()?);
+        }
+        self.expect(')')?;
+        let arity = self.gp.function_arity(function);
+        if args.len() != arity {
+            return Err(self.error_at(
+                start,
+                &format!("{} takes {} arguments, got {}", name, arity, args.len()),
+            ));
+        }
+        Ok(genome::node(Action::Function(function), args))
+    }
+
+    fn binary(
+        &self,
+        operator: char,
+        start: usize,
+        left: Genome,
+        right: Genome,
+    ) -> Result<Genome, String> {
+        let function = self.function(&operator.to_string(), start)?;
+        Ok(genome::node(Action::Function(function), vec![left, right]))
+    }
+
+    fn function(&self, name: &str, start: usize) -> Result<Function, String> {
+        self.gp
+            .find_function(name)
+            .map_err(|e| self.error_at(start, &e))
+    }
+
+    //consumes the next character if it is one of `operators`, returning it and its offset
+    fn operator(&mut self, operators: &[char]) -> Option<(char, usize)> {
+        self.skip_whitespace();
+        let next = self.peek().filter(|c| operators.contains(c))?;
+        let start = self.position;
+        self.position += next.len_utf8();
+        Some((next, start))
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", expected)))
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let text = self.text;
+        let start = self.position;
+        let length: usize = text[start..]
+            .chars()
+            .take_while(|c| f(*c))
+            .map(char::len_utf8)
+            .sum();
+        self.position += length;
+        &text[start..self.position]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn error(&self, message: &str) -> String {
+        self.error_at(self.position, message)
+    }
+
+    //`message` with the 1-based column of the byte offset `position`
+    fn error_at(&self, position: usize, message: &str) -> String {
+        format!(
+            "{} at column {}",
+            message,
+            self.text[..position].chars().count() + 1
+        )
+    }
+}
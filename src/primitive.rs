@@ -0,0 +1,348 @@
+use crate::{utils, GP};
+use wasm_bindgen::prelude::*;
+
+/// The largest arity a registered primitive may have.
+pub const MAX_ARITY: usize = 4;
+
+/// The functions a chromosome can be built from, named in `Config::chromosome_function`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Primitive {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Sin,
+    Cos,
+    //printed as exp(a, b) but raises a to the power b
+    Exp,
+    //natural logarithm
+    Log,
+    Sqrt,
+    Tanh,
+    Abs,
+    Min,
+    Max,
+}
+
+impl Primitive {
+    pub const ALL: [Primitive; 13] = [
+        Primitive::Add,
+        Primitive::Sub,
+        Primitive::Mul,
+        Primitive::Div,
+        Primitive::Sin,
+        Primitive::Cos,
+        Primitive::Exp,
+        Primitive::Log,
+        Primitive::Sqrt,
+        Primitive::Tanh,
+        Primitive::Abs,
+        Primitive::Min,
+        Primitive::Max,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Primitive> {
+        Self::ALL.iter().cloned().find(|p| p.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Primitive::Add => "+",
+            Primitive::Sub => "-",
+            Primitive::Mul => "*",
+            Primitive::Div => "/",
+            Primitive::Sin => "sin",
+            Primitive::Cos => "cos",
+            Primitive::Exp => "exp",
+            Primitive::Log => "log",
+            Primitive::Sqrt => "sqrt",
+            Primitive::Tanh => "tanh",
+            Primitive::Abs => "abs",
+            Primitive::Min => "min",
+            Primitive::Max => "max",
+        }
+    }
+
+    /// The number of children a node of this primitive has.
+    pub fn arity(self) -> usize {
+        match self {
+            Primitive::Sin
+            | Primitive::Cos
+            | Primitive::Log
+            | Primitive::Sqrt
+            | Primitive::Tanh
+            | Primitive::Abs => 1,
+            _ => 2,
+        }
+    }
+
+    /// Evaluates the primitive, `args` holds exactly `arity()` values.
+    pub fn apply(self, args: &[f64]) -> f64 {
+        match self {
+            Primitive::Add => args[0] + args[1],
+            Primitive::Sub => args[0] - args[1],
+            Primitive::Mul => args[0] * args[1],
+            Primitive::Div => args[0] / args[1],
+            Primitive::Sin => args[0].sin(),
+            Primitive::Cos => args[0].cos(),
+            Primitive::Exp => args[0].powf(args[1]),
+            Primitive::Log => args[0].ln(),
+            Primitive::Sqrt => args[0].sqrt(),
+            Primitive::Tanh => args[0].tanh(),
+            Primitive::Abs => args[0].abs(),
+            Primitive::Min => args[0].min(args[1]),
+            Primitive::Max => args[0].max(args[1]),
+        }
+    }
+
+    /// Evaluates the primitive for a whole column of cases at once. `first` holds the first
+    /// argument and receives the result, `rest` holds the other arguments' columns one after
+    /// another. Gives exactly the same values as `apply`.
+    pub fn apply_columns(self, first: &mut [f64], rest: &[f64]) {
+        let second = &rest[..rest.len().min(first.len())];
+        match self {
+            Primitive::Add => zip_with(first, second, |a, b| a + b),
+            Primitive::Sub => zip_with(first, second, |a, b| a - b),
+            Primitive::Mul => zip_with(first, second, |a, b| a * b),
+            Primitive::Div => zip_with(first, second, |a, b| a / b),
+            Primitive::Sin => map(first, f64::sin),
+            Primitive::Cos => map(first, f64::cos),
+            Primitive::Exp => zip_with(first, second, f64::powf),
+            Primitive::Log => map(first, f64::ln),
+            Primitive::Sqrt => map(first, f64::sqrt),
+            Primitive::Tanh => map(first, f64::tanh),
+            Primitive::Abs => map(first, f64::abs),
+            Primitive::Min => zip_with(first, second, f64::min),
+            Primitive::Max => zip_with(first, second, f64::max),
+        }
+    }
+
+    /// Prints the primitive applied to already printed arguments.
+    pub fn format(self, args: &[String]) -> String {
+        match self {
+            Primitive::Add | Primitive::Sub | Primitive::Mul | Primitive::Div => {
+                format!("({} {} {})", args[0], self.name(), args[1])
+            }
+            _ => format!("{}({})", self.name(), args.join(", ")),
+        }
+    }
+}
+
+fn map(values: &mut [f64], f: impl Fn(f64) -> f64) {
+    for value in values {
+        *value = f(*value);
+    }
+}
+
+fn zip_with(first: &mut [f64], second: &[f64], f: impl Fn(f64, f64) -> f64) {
+    for (a, b) in first.iter_mut().zip(second) {
+        *a = f(*a, *b);
+    }
+}
+
+/// A function node: either a built-in primitive or one registered on the `GP`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Function {
+    Builtin(Primitive),
+    //index into GP::custom_primitives
+    Custom(usize),
+}
+
+/// A primitive added at runtime through `GP::register_primitive` or `GP::register_js_primitive`.
+pub(crate) struct CustomPrimitive {
+    pub(crate) name: String,
+    pub(crate) arity: usize,
+    //printed form with {0}, {1}, ... standing for the arguments
+    pub(crate) template: Option<String>,
+    pub(crate) kernel: Kernel,
+}
+
+pub(crate) enum Kernel {
+    Builtin(Primitive),
+    Js(js_sys::Function),
+}
+
+#[wasm_bindgen]
+impl GP {
+    /// Adds a function backed by one of the built-in Rust kernels (see `Primitive`) to the
+    /// function set. `template` is the printed form, using `{0}`, `{1}`, ... for the arguments.
+    /// `name` follows the rules for column names (see `Dataset::new`). Must be called before
+    /// `init`.
+    pub fn register_primitive(
+        &mut self,
+        name: String,
+        arity: usize,
+        template: Option<String>,
+        kernel: &str,
+    ) -> Result<(), JsValue> {
+        let primitive = Primitive::from_name(kernel)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown kernel: {}", kernel)))?;
+        if primitive.arity() != arity {
+            return Err(JsValue::from_str(&format!(
+                "kernel {} takes {} arguments, not {}",
+                kernel,
+                primitive.arity(),
+                arity
+            )));
+        }
+        self.add_custom_primitive(CustomPrimitive {
+            name,
+            arity,
+            template,
+            kernel: Kernel::Builtin(primitive),
+        })
+        .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Adds a function implemented in JavaScript to the function set. `function` is called with
+    /// `arity` numbers and should return a number, anything else evaluates to NaN. `name` follows
+    /// the rules for column names. Must be called before `init`.
+    pub fn register_js_primitive(
+        &mut self,
+        name: String,
+        arity: usize,
+        template: Option<String>,
+        function: js_sys::Function,
+    ) -> Result<(), JsValue> {
+        self.add_custom_primitive(CustomPrimitive {
+            name,
+            arity,
+            template,
+            kernel: Kernel::Js(function),
+        })
+        .map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+impl GP {
+    pub(crate) fn add_custom_primitive(
+        &mut self,
+        primitive: CustomPrimitive,
+    ) -> Result<(), String> {
+        if !self.population.is_empty() {
+            return Err("primitives must be registered before init".to_string());
+        }
+        if primitive.arity == 0 || primitive.arity > MAX_ARITY {
+            return Err(format!(
+                "arity must be between 1 and {}, got {}",
+                MAX_ARITY, primitive.arity
+            ));
+        }
+        if Primitive::from_name(&primitive.name).is_some() {
+            return Err(format!(
+                "{} is a built-in primitive, add it to chromosome_function instead",
+                primitive.name
+            ));
+        }
+        if !utils::is_name(&primitive.name) {
+            return Err(format!("invalid primitive name: {:?}", primitive.name));
+        }
+        if self.dataset.column_index(&primitive.name).is_some()
+            || self
+                .custom_primitives
+                .iter()
+                .any(|custom| custom.name == primitive.name)
+        {
+            return Err(format!("the name {:?} is already in use", primitive.name));
+        }
+        //the config keeps naming only built-in primitives, so it stays valid on its own
+        self.functions
+            .push(Function::Custom(self.custom_primitives.len()));
+        self.custom_primitives.push(primitive);
+        Ok(())
+    }
+
+    pub(crate) fn function_arity(&self, function: Function) -> usize {
+        match function {
+            Function::Builtin(primitive) => primitive.arity(),
+            Function::Custom(index) => self.custom_primitives[index].arity,
+        }
+    }
+
+    pub(crate) fn function_name(&self, function: Function) -> &str {
+        match function {
+            Function::Builtin(primitive) => primitive.name(),
+            Function::Custom(index) => &self.custom_primitives[index].name,
+        }
+    }
+
+    pub(crate) fn apply_function(&self, function: Function, args: &[f64]) -> f64 {
+        match function {
+            Function::Builtin(primitive) => primitive.apply(args),
+            Function::Custom(index) => match &self.custom_primitives[index].kernel {
+                Kernel::Builtin(primitive) => primitive.apply(args),
+                Kernel::Js(function) => {
+                    let result = match args {
+                        [a] => function.call1(&JsValue::NULL, &JsValue::from_f64(*a)),
+                        [a, b] => function.call2(
+                            &JsValue::NULL,
+                            &JsValue::from_f64(*a),
+                            &JsValue::from_f64(*b),
+                        ),
+                        _ => {
+                            let js_args: js_sys::Array =
+                                args.iter().map(|arg| JsValue::from_f64(*arg)).collect();
+                            function.apply(&JsValue::NULL, &js_args)
+                        }
+                    };
+                    result
+                        .ok()
+                        .and_then(|value| value.as_f64())
+                        .unwrap_or(f64::NAN)
+                }
+            },
+        }
+    }
+
+    pub(crate) fn format_function(&self, function: Function, args: &[String]) -> String {
+        match function {
+            Function::Builtin(primitive) => primitive.format(args),
+            Function::Custom(index) => {
+                let primitive = &self.custom_primitives[index];
+                match &primitive.template {
+                    Some(template) => args
+                        .iter()
+                        .enumerate()
+                        .fold(template.clone(), |printed, (index, arg)| {
+                            printed.replace(&format!("{{{}}}", index), arg)
+                        }),
+                    None => format!("{}({})", primitive.name, args.join(", ")),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Dataset};
+
+    fn register(gp: &mut GP, name: &str) -> Result<(), String> {
+        gp.add_custom_primitive(CustomPrimitive {
+            name: name.to_string(),
+            arity: 1,
+            template: None,
+            kernel: Kernel::Builtin(Primitive::Abs),
+        })
+    }
+
+    #[test]
+    fn primitive_names_read_back() {
+        let dataset = Dataset::from_pairs(&[1.0, 2.0]);
+        let mut gp = GP::from_dataset(dataset, Config::new()).unwrap();
+        for name in ["my op", "2abs", "a(b", "", "R", "NaN", "inf"] {
+            assert_eq!(
+                register(&mut gp, name).unwrap_err(),
+                format!("invalid primitive name: {:?}", name)
+            );
+        }
+        assert_eq!(
+            register(&mut gp, "x").unwrap_err(),
+            "the name \"x\" is already in use"
+        );
+        register(&mut gp, "my_op2").unwrap();
+        assert!(gp.measure_expression("my_op2(x)").is_ok());
+        assert!(register(&mut gp, "my_op2").is_err());
+    }
+}
@@ -0,0 +1,304 @@
+//! Chromosomes compiled to postfix programs, evaluated over the whole dataset a column at a time.
+
+use crate::genome::{self, Gene};
+use crate::primitive::{Function, Kernel, MAX_ARITY};
+use crate::{Action, Dataset, GP};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Op {
+    Const(f64),
+    Var(usize),
+    Call(Function, usize),
+}
+
+/// A chromosome flattened into postfix order: every argument comes before the function using it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Program {
+    ops: Vec<Op>,
+    //the most values on the stack at any point
+    max_stack: usize,
+}
+
+impl Program {
+    pub(crate) fn compile(chromosome: &[Gene]) -> Program {
+        let mut program = Program {
+            ops: Vec::new(),
+            max_stack: 0,
+        };
+        program.push_node(chromosome, 0);
+        program
+    }
+
+    //`depth` is the stack size before the node's value is pushed
+    fn push_node(&mut self, genes: &[Gene], depth: usize) {
+        let mut arity = 0;
+        for arg in genome::args(genes) {
+            self.push_node(arg, depth + arity);
+            arity += 1;
+        }
+        self.ops.push(match genes[0].action {
+            Action::Function(function) => Op::Call(function, arity),
+            Action::Terminal(value) => Op::Const(value),
+            Action::Var(index) => Op::Var(index),
+        });
+        self.max_stack = self.max_stack.max(depth + 1);
+    }
+}
+
+impl Program {
+    /// Runs the program for every row of `dataset`, returning one output per row. `call`
+    /// evaluates a function given its first argument's column, which receives the result, and
+    /// the other arguments' columns one after another.
+    pub(crate) fn run(
+        &self,
+        dataset: &Dataset,
+        call: impl Fn(Function, &mut [f64], &[f64]),
+    ) -> Vec<f64> {
+        let rows = dataset.rows();
+        //slot i of the stack is stack[i * rows..(i + 1) * rows]
+        let mut stack = vec![0.0; self.max_stack * rows];
+        let mut top = 0;
+        for op in &self.ops {
+            match *op {
+                Op::Const(value) => {
+                    stack[top * rows..(top + 1) * rows].fill(value);
+                    top += 1;
+                }
+                Op::Var(index) => {
+                    stack[top * rows..(top + 1) * rows].copy_from_slice(dataset.column(index));
+                    top += 1;
+                }
+                Op::Call(function, arity) => {
+                    top -= arity;
+                    let (first, rest) = stack[top * rows..(top + arity) * rows].split_at_mut(rows);
+                    call(function, first, rest);
+                    top += 1;
+                }
+            }
+        }
+        stack.truncate(rows);
+        stack
+    }
+}
+
+impl GP {
+    /// Runs `program` for every row of the dataset, returning one output per row.
+    pub(crate) fn run_program(&self, program: &Program) -> Vec<f64> {
+        program.run(&self.dataset, |function, first, rest| {
+            self.call_columns(function, first, rest)
+        })
+    }
+
+    fn call_columns(&self, function: Function, first: &mut [f64], rest: &[f64]) {
+        let primitive = match function {
+            Function::Builtin(primitive) => Some(primitive),
+            Function::Custom(index) => match self.custom_primitives[index].kernel {
+                Kernel::Builtin(primitive) => Some(primitive),
+                Kernel::Js(_) => None,
+            },
+        };
+        match primitive {
+            Some(primitive) => primitive.apply_columns(first, rest),
+            //JS kernels take one case at a time
+            None => call_rows(first, rest, |args| self.apply_function(function, args)),
+        }
+    }
+}
+
+//calls `apply` with the arguments of each row in turn, for functions without a column kernel
+fn call_rows(first: &mut [f64], rest: &[f64], apply: impl Fn(&[f64]) -> f64) {
+    let rows = first.len();
+    let arity = rest.len() / rows + 1;
+    let mut args = [0.0; MAX_ARITY];
+    for row in 0..rows {
+        args[0] = first[row];
+        for arg in 1..arity {
+            args[arg] = rest[(arg - 1) * rows + row];
+        }
+        first[row] = apply(&args[..arity]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genome::Genome;
+    use crate::primitive::Primitive;
+
+    fn dataset() -> Dataset {
+        let columns = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        #[rustfmt::skip]
+        let values = vec![
+            -2.5, 0.0, 1.0, 7.0,
+            0.0, -0.0, 2.0, 1.0,
+            0.5, 3.0, -1.0, 0.0,
+            1e300, -4.0, f64::NAN, 2.0,
+            -0.0, f64::INFINITY, 0.25, 3.0,
+        ];
+        Dataset::from_values(columns, values).unwrap()
+    }
+
+    fn leaf(action: Action) -> Genome {
+        vec![Gene::leaf(action)]
+    }
+
+    fn call(function: Function, args: Vec<Genome>) -> Genome {
+        genome::node(Action::Function(function), args)
+    }
+
+    //custom primitives with more arguments than any built-in
+    fn custom(index: usize, args: &[f64]) -> f64 {
+        match index {
+            0 => args[0] * args[1] + args[2],
+            _ => args[0] - args[1] / args[2] + args[3].abs(),
+        }
+    }
+
+    //the chromosome walked once per row, as it was evaluated before being compiled
+    fn run_rows(genes: &[Gene], dataset: &Dataset, row: usize) -> f64 {
+        let args: Vec<f64> = genome::args(genes)
+            .map(|arg| run_rows(arg, dataset, row))
+            .collect();
+        match genes[0].action {
+            Action::Function(Function::Builtin(primitive)) => primitive.apply(&args),
+            Action::Function(Function::Custom(index)) => custom(index, &args),
+            Action::Terminal(value) => value,
+            Action::Var(index) => dataset.column(index)[row],
+        }
+    }
+
+    fn assert_runs_like_rows(chromosome: &[Gene]) {
+        let dataset = dataset();
+        let outputs =
+            Program::compile(chromosome).run(&dataset, |function, first, rest| match function {
+                Function::Builtin(primitive) => primitive.apply_columns(first, rest),
+                Function::Custom(index) => call_rows(first, rest, |args| custom(index, args)),
+            });
+        assert_eq!(outputs.len(), dataset.rows());
+        for (row, output) in outputs.iter().enumerate() {
+            let expected = run_rows(chromosome, &dataset, row);
+            //NaN compares by its bits, so a NaN from a different operation is still caught
+            assert_eq!(
+                output.to_bits(),
+                expected.to_bits(),
+                "row {} of {:?}",
+                row,
+                chromosome
+            );
+        }
+    }
+
+    #[test]
+    fn every_builtin_runs_like_per_row_evaluation() {
+        let operands: Vec<Genome> = vec![
+            leaf(Action::Var(0)),
+            leaf(Action::Var(1)),
+            leaf(Action::Var(2)),
+            leaf(Action::Terminal(2.0)),
+            leaf(Action::Terminal(-0.5)),
+            leaf(Action::Terminal(0.0)),
+            leaf(Action::Terminal(f64::NAN)),
+            leaf(Action::Terminal(f64::INFINITY)),
+            leaf(Action::Terminal(f64::NEG_INFINITY)),
+        ];
+        for primitive in Primitive::ALL.iter().cloned() {
+            let function = Function::Builtin(primitive);
+            for a in &operands {
+                if primitive.arity() == 1 {
+                    assert_runs_like_rows(&call(function, vec![a.clone()]));
+                    continue;
+                }
+                for b in &operands {
+                    assert_runs_like_rows(&call(function, vec![a.clone(), b.clone()]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn nested_trees_over_several_columns_run_like_per_row_evaluation() {
+        let builtin = |primitive, args| call(Function::Builtin(primitive), args);
+        //(a + sin(b)) * c / min(exp(a, 2), log(c - -inf))
+        let chromosome = builtin(
+            Primitive::Div,
+            vec![
+                builtin(
+                    Primitive::Mul,
+                    vec![
+                        builtin(
+                            Primitive::Add,
+                            vec![
+                                leaf(Action::Var(0)),
+                                builtin(Primitive::Sin, vec![leaf(Action::Var(1))]),
+                            ],
+                        ),
+                        leaf(Action::Var(2)),
+                    ],
+                ),
+                builtin(
+                    Primitive::Min,
+                    vec![
+                        builtin(
+                            Primitive::Exp,
+                            vec![leaf(Action::Var(0)), leaf(Action::Terminal(2.0))],
+                        ),
+                        builtin(
+                            Primitive::Log,
+                            vec![builtin(
+                                Primitive::Sub,
+                                vec![
+                                    leaf(Action::Var(2)),
+                                    leaf(Action::Terminal(f64::NEG_INFINITY)),
+                                ],
+                            )],
+                        ),
+                    ],
+                ),
+            ],
+        );
+        assert_runs_like_rows(&chromosome);
+    }
+
+    #[test]
+    fn custom_primitives_of_arity_three_and_four_get_every_argument() {
+        let sum = |a, b| {
+            call(
+                Function::Builtin(Primitive::Add),
+                vec![leaf(Action::Var(a)), leaf(Action::Var(b))],
+            )
+        };
+        //f4(f3(a, b + c, NaN), c, a + b, f3(c, 2, a)) keeps several columns on the stack at once
+        let chromosome = call(
+            Function::Custom(1),
+            vec![
+                call(
+                    Function::Custom(0),
+                    vec![
+                        leaf(Action::Var(0)),
+                        sum(1, 2),
+                        leaf(Action::Terminal(f64::NAN)),
+                    ],
+                ),
+                leaf(Action::Var(2)),
+                sum(0, 1),
+                call(
+                    Function::Custom(0),
+                    vec![
+                        leaf(Action::Var(2)),
+                        leaf(Action::Terminal(2.0)),
+                        leaf(Action::Var(0)),
+                    ],
+                ),
+            ],
+        );
+        assert_runs_like_rows(&chromosome);
+        assert_runs_like_rows(&call(
+            Function::Custom(0),
+            vec![
+                leaf(Action::Var(1)),
+                leaf(Action::Terminal(f64::INFINITY)),
+                leaf(Action::Var(0)),
+            ],
+        ));
+    }
+}
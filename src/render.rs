@@ -0,0 +1,366 @@
+//! Printing chromosomes for people: infix with only the parentheses it needs, LaTeX and
+//! presentation MathML, chosen by the `format` argument of `GP::format_expression`, `GP::tick`,
+//! `GP::run`, `GP::population` and `GP::hall_of_fame`.
+//!
+//! "infix" is `chromosome_to_string`'s fully parenthesised form. "minimal" drops the parentheses
+//! that operator precedence makes redundant but keeps the ones needed to read back the same tree,
+//! so `(a + b) + c` prints as `a + b + c` but `a + (b + c)` keeps its parentheses. LaTeX and
+//! MathML also drop parentheses that only change the order of evaluation, and write division as
+//! a fraction and `exp(a, b)` as a power.
+
+use crate::genome::{self, Gene};
+use crate::primitive::{Function, Primitive};
+use crate::{simplify, Action, TickResult, GP};
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Format {
+    Infix,
+    Minimal,
+    Latex,
+    MathMl,
+}
+
+//how tightly a printed subtree binds, parentheses are added around looser operands
+const SUM: u8 = 1;
+const NEGATIVE: u8 = 2;
+const PRODUCT: u8 = 3;
+const POWER: u8 = 4;
+//function calls and fractions, which still need parentheses as the base of a power
+const CALL: u8 = 5;
+const ATOM: u8 = 6;
+
+struct Rendered {
+    text: String,
+    precedence: u8,
+}
+
+impl Format {
+    pub(crate) fn from_name(name: &str) -> Result<Format, String> {
+        match name {
+            "infix" => Ok(Format::Infix),
+            "minimal" => Ok(Format::Minimal),
+            "latex" => Ok(Format::Latex),
+            "mathml" => Ok(Format::MathMl),
+            _ => Err(format!(
+                "unknown format: {}, expected infix, minimal, latex or mathml",
+                name
+            )),
+        }
+    }
+
+    //whether parentheses that only regroup + and * may be dropped
+    fn is_math(self) -> bool {
+        matches!(self, Format::Latex | Format::MathMl)
+    }
+
+    fn parenthesize(self, text: &str) -> String {
+        match self {
+            Format::Latex => format!("\\left({}\\right)", text),
+            Format::MathMl => format!("<mrow><mo>(</mo>{}<mo>)</mo></mrow>", text),
+            _ => format!("({})", text),
+        }
+    }
+
+    fn number(self, value: f64) -> Rendered {
+        let text = match self {
+            Format::Latex if value.is_infinite() => {
+                format!("{}\\infty", if value < 0.0 { "-" } else { "" })
+            }
+            Format::Latex if value.is_nan() => "\\mathrm{NaN}".to_string(),
+            Format::MathMl if value.is_nan() => "<mi>NaN</mi>".to_string(),
+            Format::MathMl => {
+                let magnitude = if value.is_infinite() {
+                    "<mi>&#x221E;</mi>".to_string()
+                } else {
+                    format!("<mn>{}</mn>", value.abs())
+                };
+                if value.is_sign_negative() {
+                    format!("<mrow><mo>&#x2212;</mo>{}</mrow>", magnitude)
+                } else {
+                    magnitude
+                }
+            }
+            _ => value.to_string(),
+        };
+        Rendered {
+            text,
+            precedence: if value.is_sign_negative() && !value.is_nan() {
+                NEGATIVE
+            } else {
+                ATOM
+            },
+        }
+    }
+
+    fn variable(self, name: &str) -> String {
+        match self {
+            Format::Latex if name.chars().count() == 1 => escape_latex(name),
+            Format::Latex => format!("\\mathrm{{{}}}", escape_latex(name)),
+            Format::MathMl => format!("<mi>{}</mi>", escape_xml(name)),
+            _ => name.to_string(),
+        }
+    }
+
+    //`name(args)`, with `name` already in this format
+    fn call(self, name: &str, args: &[Rendered]) -> Rendered {
+        let separator = match self {
+            Format::MathMl => "<mo>,</mo>",
+            _ => ", ",
+        };
+        let args: Vec<&str> = args.iter().map(|arg| arg.text.as_str()).collect();
+        let text = match self {
+            Format::Latex => format!("{}{}", name, self.parenthesize(&args.join(separator))),
+            Format::MathMl => format!(
+                "<mrow>{}<mo>&#x2061;</mo>{}</mrow>",
+                name,
+                self.parenthesize(&args.join(separator))
+            ),
+            _ => format!("{}({})", name, args.join(separator)),
+        };
+        Rendered {
+            text,
+            precedence: CALL,
+        }
+    }
+
+    fn named_call(self, name: &str, args: &[Rendered]) -> Rendered {
+        let name = match self {
+            Format::Latex => match name {
+                "sin" | "cos" | "tanh" | "min" | "max" => format!("\\{}", name),
+                "log" => "\\ln".to_string(),
+                _ => format!("\\operatorname{{{}}}", escape_latex(name)),
+            },
+            Format::MathMl => match name {
+                "log" => "<mi>ln</mi>".to_string(),
+                _ => format!("<mi>{}</mi>", escape_xml(name)),
+            },
+            _ => name.to_string(),
+        };
+        self.call(&name, args)
+    }
+
+    fn binary(self, primitive: Primitive, left: Rendered, right: Rendered) -> Rendered {
+        let precedence = match primitive {
+            Primitive::Add | Primitive::Sub => SUM,
+            _ => PRODUCT,
+        };
+        //the right operand also needs parentheses at the same precedence, unless regrouping does
+        //not change the meaning
+        let regroups = self.is_math() && matches!(primitive, Primitive::Add | Primitive::Mul);
+        let left = self.operand(left, precedence);
+        let right_precedence = match right.precedence {
+            //a sign straight after an operator reads badly in a formula, x - (-3) rather than x - -3
+            NEGATIVE if self.is_math() => ATOM,
+            _ if regroups => precedence,
+            _ => precedence + 1,
+        };
+        let right = self.operand(right, right_precedence);
+        let operator = match (self, primitive) {
+            (Format::Latex, Primitive::Mul) => " \\cdot ".to_string(),
+            (Format::MathMl, Primitive::Add) => "<mo>+</mo>".to_string(),
+            (Format::MathMl, Primitive::Sub) => "<mo>&#x2212;</mo>".to_string(),
+            (Format::MathMl, Primitive::Mul) => "<mo>&#x22C5;</mo>".to_string(),
+            _ => format!(" {} ", primitive.name()),
+        };
+        let text = match self {
+            Format::MathMl => format!("<mrow>{}{}{}</mrow>", left, operator, right),
+            _ => format!("{}{}{}", left, operator, right),
+        };
+        Rendered { text, precedence }
+    }
+
+    //`operand` as text, in parentheses if it binds looser than `precedence`
+    fn operand(self, operand: Rendered, precedence: u8) -> String {
+        if operand.precedence < precedence {
+            self.parenthesize(&operand.text)
+        } else {
+            operand.text
+        }
+    }
+
+    fn primitive(self, primitive: Primitive, mut args: Vec<Rendered>) -> Rendered {
+        match (self, primitive) {
+            (Format::Infix, _) => unreachable!("infix is printed by chromosome_to_string"),
+            (_, Primitive::Add) | (_, Primitive::Sub) | (_, Primitive::Mul) => {
+                let right = args.pop().unwrap();
+                self.binary(primitive, args.pop().unwrap(), right)
+            }
+            (Format::Minimal, Primitive::Div) => {
+                let right = args.pop().unwrap();
+                self.binary(primitive, args.pop().unwrap(), right)
+            }
+            (Format::Minimal, _) => self.named_call(primitive.name(), &args),
+            (_, Primitive::Div) => {
+                let text = match self {
+                    Format::Latex => format!("\\frac{{{}}}{{{}}}", args[0].text, args[1].text),
+                    _ => format!("<mfrac>{}{}</mfrac>", args[0].text, args[1].text),
+                };
+                Rendered {
+                    text,
+                    precedence: CALL,
+                }
+            }
+            (_, Primitive::Exp) => {
+                let exponent = args.pop().unwrap().text;
+                let base = args.pop().unwrap();
+                let base = self.operand(base, ATOM);
+                let text = match self {
+                    Format::Latex => format!("{{{}}}^{{{}}}", base, exponent),
+                    _ => format!("<msup>{}<mrow>{}</mrow></msup>", base, exponent),
+                };
+                Rendered {
+                    text,
+                    precedence: POWER,
+                }
+            }
+            (_, Primitive::Sqrt) => {
+                let text = match self {
+                    Format::Latex => format!("\\sqrt{{{}}}", args[0].text),
+                    _ => format!("<msqrt>{}</msqrt>", args[0].text),
+                };
+                Rendered {
+                    text,
+                    precedence: CALL,
+                }
+            }
+            (_, Primitive::Abs) => {
+                let text = match self {
+                    Format::Latex => format!("\\left|{}\\right|", args[0].text),
+                    _ => format!("<mrow><mo>|</mo>{}<mo>|</mo></mrow>", args[0].text),
+                };
+                Rendered {
+                    text,
+                    precedence: CALL,
+                }
+            }
+            _ => self.named_call(primitive.name(), &args),
+        }
+    }
+}
+
+fn escape_latex(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '_' | '#' | '$' | '%' | '&' | '{' | '}' => format!("\\{}", c),
+            '\\' => "\\textbackslash{}".to_string(),
+            '^' => "\\textasciicircum{}".to_string(),
+            '~' => "\\textasciitilde{}".to_string(),
+            _ => c.to_string(),
+        })
+        .collect()
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[wasm_bindgen]
+impl GP {
+    /// Prints `expression` (infix or JSON, see `seed_expression`), or the current best member when
+    /// it is `null`, in `format`: "infix", "minimal" for infix without redundant parentheses,
+    /// "latex" or "mathml".
+    pub fn format_expression(
+        &self,
+        format: &str,
+        expression: Option<String>,
+    ) -> Result<String, JsValue> {
+        self.render_expression(format, expression.as_deref())
+            .map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+impl GP {
+    /// The Rust equivalent of `format_expression`.
+    pub fn render_expression(
+        &self,
+        format: &str,
+        expression: Option<&str>,
+    ) -> Result<String, String> {
+        let format = Format::from_name(format)?;
+        match expression {
+            Some(expression) => {
+                Ok(self.render_chromosome(&self.parse_chromosome(expression)?, format))
+            }
+            None => match self.population.last() {
+                Some(best) => Ok(self.render_chromosome(&best.chromosome, format)),
+                None => Err("there is no best member before init".to_string()),
+            },
+        }
+    }
+
+    //`result`, which reports the current best member, with `best` and `simplified` in `format`
+    pub(crate) fn result_in(&self, mut result: TickResult, format: Format) -> TickResult {
+        if let Some(best) = self.population.last().filter(|_| format != Format::Infix) {
+            result.best = self.render_chromosome(&best.chromosome, format);
+            result.simplified =
+                self.render_chromosome(&simplify::simplify(&best.chromosome), format);
+        }
+        result
+    }
+
+    pub(crate) fn render_chromosome(&self, chromosome: &[Gene], format: Format) -> String {
+        match format {
+            Format::Infix => self.chromosome_to_string(chromosome),
+            Format::MathMl => format!(
+                "<math xmlns=\"http://www.w3.org/1998/Math/MathML\">{}</math>",
+                self.render(chromosome, format).text
+            ),
+            _ => self.render(chromosome, format).text,
+        }
+    }
+
+    fn render(&self, genes: &[Gene], format: Format) -> Rendered {
+        match genes[0].action {
+            Action::Function(function) => {
+                let args: Vec<Rendered> = genome::args(genes)
+                    .map(|arg| self.render(arg, format))
+                    .collect();
+                match function {
+                    Function::Builtin(primitive) => format.primitive(primitive, args),
+                    Function::Custom(_) => format.named_call(self.function_name(function), &args),
+                }
+            }
+            Action::Terminal(value) => format.number(value),
+            Action::Var(index) => Rendered {
+                text: format.variable(self.dataset.column_name(index)),
+                precedence: ATOM,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Dataset};
+
+    #[test]
+    fn results_are_printed_in_the_requested_format() {
+        let mut config = Config::new();
+        config.set_pop_size(50);
+        config.set_seed(Some(4));
+        let dataset = Dataset::from_pairs(&[1.0, 2.0, 2.0, 5.0, 3.0, 10.0]);
+        let mut gp = GP::from_dataset(dataset, config).unwrap();
+        gp.init();
+        let infix = gp.step().unwrap();
+        let latex = gp.result_in(infix.clone(), Format::Latex);
+        assert_eq!(latex.best, gp.render_expression("latex", None).unwrap());
+        assert_eq!(
+            latex.simplified,
+            gp.render_expression("latex", Some(&infix.simplified))
+                .unwrap()
+        );
+        assert_eq!(gp.result_in(infix.clone(), Format::Infix).best, infix.best);
+    }
+
+    #[test]
+    fn latex_special_characters_are_escaped() {
+        assert_eq!(
+            escape_latex(r"a_b#$%&{}\^~"),
+            r"a\_b\#\$\%\&\{\}\textbackslash{}\textasciicircum{}\textasciitilde{}"
+        );
+    }
+}
@@ -0,0 +1,36 @@
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+
+/// What `GP::tick` reports after each generation.
+///
+/// Every field serializes to plain JSON: a non-finite fitness becomes `null` and the seed is a
+/// string, because a `u64` does not fit in a JS number.
+#[derive(Debug, Clone, Serialize)]
+pub struct TickResult {
+    pub done: bool,
+    pub gen: u16,
+    pub fitness: Option<f64>,
+    pub best: String,
+    //`best` after algebraic simplification, for display
+    pub simplified: String,
+    pub chromosome: serde_json::Value,
+    pub fitness_evaluations: usize,
+    pub seed: String,
+    pub stop_reason: Option<&'static str>,
+}
+
+/// One member of the current population, see `GP::population`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PopulationEntry {
+    pub fitness: Option<f64>,
+    pub expression: String,
+    pub size: usize,
+    pub depth: u16,
+}
+
+impl TickResult {
+    pub(crate) fn to_js(&self) -> JsValue {
+        self.serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .unwrap()
+    }
+}
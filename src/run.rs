@@ -0,0 +1,110 @@
+use crate::render::Format;
+use crate::{utils, StopReason, TickResult, GP};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+impl GP {
+    /// Runs up to `generations` generations in one call, so a Web Worker or an animation frame
+    /// can advance the run in slices. Returns early once `max_ms` milliseconds have passed
+    /// (checked after each generation), when the run is done or paused, or when `progress`,
+    /// called with every generation's `tick` result, returns `false` or throws. Returns the last
+    /// result. `format` is as for `tick`. Throws if `init` has not been called.
+    pub fn run(
+        &mut self,
+        generations: u16,
+        max_ms: Option<f64>,
+        progress: Option<js_sys::Function>,
+        format: Option<String>,
+    ) -> Result<JsValue, JsValue> {
+        let format = Format::from_name(format.as_deref().unwrap_or("infix"))
+            .map_err(|e| JsValue::from_str(&e))?;
+        let mut exception = None;
+        let result = self.run_slice(generations, max_ms, format, |result| match &progress {
+            Some(progress) => match progress.call1(&JsValue::NULL, &result.to_js()) {
+                Ok(keep_going) => keep_going.as_bool() != Some(false),
+                Err(e) => {
+                    exception = Some(e);
+                    false
+                }
+            },
+            None => true,
+        });
+        if let Some(exception) = exception {
+            log!(
+                self,
+                Warn,
+                "gen {}: the progress callback threw {:?}, stopping",
+                self.gen,
+                exception
+            );
+        }
+        result
+            .map(|result| result.to_js())
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Stops advancing the run until `resume`: `run` and `tick` report the current best without
+    /// running a generation. Time spent paused does not count towards `Config::max_time_ms`.
+    pub fn pause(&mut self) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(utils::now_ms());
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.started_at += utils::now_ms() - paused_at;
+        }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// Ends the run for good: the next result is done, with `stop_reason` "cancelled".
+    pub fn cancel(&mut self) {
+        if self.stop_reason.is_none() {
+            self.stop_reason = Some(StopReason::Cancelled);
+            self.log_run_completed();
+        }
+    }
+}
+
+impl GP {
+    /// The Rust equivalent of `run`, `progress` returns whether to keep going.
+    pub fn run_for(
+        &mut self,
+        generations: u16,
+        max_ms: Option<f64>,
+        progress: impl FnMut(&TickResult) -> bool,
+    ) -> Result<TickResult, String> {
+        self.run_slice(generations, max_ms, Format::Infix, progress)
+    }
+
+    fn run_slice(
+        &mut self,
+        generations: u16,
+        max_ms: Option<f64>,
+        format: Format,
+        mut progress: impl FnMut(&TickResult) -> bool,
+    ) -> Result<TickResult, String> {
+        self.check_started()?;
+        let started_at = utils::now_ms();
+        let mut result = self.result_in(self.result(), format);
+        for _ in 0..generations {
+            if result.done || self.paused() {
+                break;
+            }
+            let next = self.advance();
+            result = self.result_in(next, format);
+            if !progress(&result) {
+                break;
+            }
+            if max_ms.is_some_and(|max_ms| utils::now_ms() - started_at >= max_ms) {
+                break;
+            }
+        }
+        Ok(result)
+    }
+}
@@ -0,0 +1,183 @@
+//! Parent selection strategies, chosen by `Config::selection`.
+//!
+//! Strategies only look at fitness values, so they can be exercised without building a `GP`.
+
+use crate::fitness::{FitnessMetric, Objective};
+use crate::Config;
+use rand::prelude::*;
+
+pub trait Selection {
+    /// Picks the index of one member of the population.
+    fn select(&self, fitness: &[f64], rng: &mut dyn RngCore) -> usize;
+
+    /// Picks `count` indexes, with replacement.
+    fn select_many(&self, fitness: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        (0..count).map(|_| self.select(fitness, rng)).collect()
+    }
+}
+
+/// Builds the strategy named by `config.selection`.
+pub fn from_config(config: &Config) -> Result<Box<dyn Selection>, String> {
+    let objective = FitnessMetric::from_config(config)?.objective();
+    match config.selection.as_str() {
+        "tournament" => Ok(Box::new(Tournament {
+            size: config.tournament_size as usize,
+            objective,
+        })),
+        "roulette" => Ok(Box::new(Roulette { objective })),
+        "rank" => Ok(Box::new(Rank { objective })),
+        "sus" => Ok(Box::new(StochasticUniversal { objective })),
+        "random" => Ok(Box::new(Uniform)),
+        _ => Err(format!("unknown selection: {}", config.selection)),
+    }
+}
+
+/// Samples `size` members uniformly and keeps the fittest.
+pub struct Tournament {
+    pub size: usize,
+    pub objective: Objective,
+}
+
+impl Selection for Tournament {
+    fn select(&self, fitness: &[f64], rng: &mut dyn RngCore) -> usize {
+        let mut best = rng.gen_range(0, fitness.len());
+        for _ in 1..self.size {
+            let challenger = rng.gen_range(0, fitness.len());
+            if self.objective.is_better(fitness[challenger], fitness[best]) {
+                best = challenger;
+            }
+        }
+        best
+    }
+}
+
+/// Fitness-proportionate selection.
+pub struct Roulette {
+    pub objective: Objective,
+}
+
+impl Selection for Roulette {
+    fn select(&self, fitness: &[f64], rng: &mut dyn RngCore) -> usize {
+        self.select_many(fitness, 1, rng)[0]
+    }
+
+    fn select_many(&self, fitness: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        spin(&proportional_weights(fitness, self.objective), count, rng)
+    }
+}
+
+/// Selection weighted by position in the sorted population rather than raw fitness.
+pub struct Rank {
+    pub objective: Objective,
+}
+
+impl Selection for Rank {
+    fn select(&self, fitness: &[f64], rng: &mut dyn RngCore) -> usize {
+        self.select_many(fitness, 1, rng)[0]
+    }
+
+    fn select_many(&self, fitness: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        spin(&rank_weights(fitness, self.objective), count, rng)
+    }
+}
+
+/// Fitness-proportionate selection using evenly spaced pointers, which keeps the
+/// number of copies of each member close to its expected value.
+pub struct StochasticUniversal {
+    pub objective: Objective,
+}
+
+impl Selection for StochasticUniversal {
+    fn select(&self, fitness: &[f64], rng: &mut dyn RngCore) -> usize {
+        self.select_many(fitness, 1, rng)[0]
+    }
+
+    fn select_many(&self, fitness: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        let weights = proportional_weights(fitness, self.objective);
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return Uniform.select_many(fitness, count, rng);
+        }
+        let step = total / count as f64;
+        let mut pointer = rng.gen::<f64>() * step;
+        let mut cumulative = 0.0;
+        let mut index = 0;
+        let mut selected = Vec::with_capacity(count);
+        for (i, weight) in weights.iter().enumerate() {
+            cumulative += weight;
+            while pointer < cumulative && selected.len() < count {
+                selected.push(i);
+                pointer += step;
+            }
+            index = i;
+        }
+        //rounding can leave the last pointer just past the end
+        while selected.len() < count {
+            selected.push(index);
+        }
+        //the pointers visit members in order, shuffle so pairs are not always neighbours
+        selected.shuffle(rng);
+        selected
+    }
+}
+
+/// Ignores fitness entirely.
+pub struct Uniform;
+
+impl Selection for Uniform {
+    fn select(&self, fitness: &[f64], rng: &mut dyn RngCore) -> usize {
+        rng.gen_range(0, fitness.len())
+    }
+}
+
+//distance from the best member, mapped so the best gets weight 1 and worse members approach 0
+fn proportional_weights(fitness: &[f64], objective: Objective) -> Vec<f64> {
+    let best =
+        fitness
+            .iter()
+            .cloned()
+            .filter(|f| f.is_finite())
+            .fold(None, |best: Option<f64>, f| match best {
+                Some(b) if !objective.is_better(f, b) => Some(b),
+                _ => Some(f),
+            });
+    fitness
+        .iter()
+        .map(|f| match best {
+            Some(best) if f.is_finite() => 1.0 / (1.0 + (f - best).abs()),
+            _ => 0.0,
+        })
+        .collect()
+}
+
+//worst member gets weight 1, best gets weight n
+fn rank_weights(fitness: &[f64], objective: Objective) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..fitness.len()).collect();
+    order.sort_by(|a, b| objective.compare(fitness[*a], fitness[*b]));
+    let mut weights = vec![0.0; fitness.len()];
+    for (rank, index) in order.into_iter().enumerate() {
+        weights[index] = (rank + 1) as f64;
+    }
+    weights
+}
+
+//pick `count` indexes, each with probability proportional to its weight
+fn spin(weights: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+    let mut cumulative = Vec::with_capacity(weights.len());
+    let mut total = 0.0;
+    for weight in weights {
+        total += weight;
+        cumulative.push(total);
+    }
+    if total <= 0.0 {
+        return Uniform.select_many(weights, count, rng);
+    }
+    (0..count)
+        .map(|_| {
+            let target = rng.gen::<f64>() * total;
+            cumulative
+                .partition_point(|c| *c <= target)
+                .min(weights.len() - 1)
+        })
+        .collect()
+}
@@ -0,0 +1,159 @@
+//! Algebraic simplification of chromosomes, for reporting and as an optional editing operator
+//! (`Config::simplify_interval`).
+//!
+//! Constant subtrees of built-in primitives are folded, identities such as `x + 0`, `x * 1`,
+//! `x * 0`, `x - x`, `x / x` and `exp(x, 1)` are applied, and chains like `(x + 2) + 3` collapse
+//! into `x + 5`. Some of these only hold for finite values (`x * 0` is NaN for infinite `x`), and
+//! reassociating constants can round differently, so the editing operator re-measures each
+//! simplified member and only keeps it when its fitness is unchanged.
+
+use crate::genome::{self, Gene, Genome};
+use crate::primitive::{Function, Primitive};
+use crate::{Action, Member, GP};
+
+/// A simplified copy of `genes`. Registered primitives are never folded, only their arguments
+/// are simplified.
+pub(crate) fn simplify(genes: &[Gene]) -> Genome {
+    match genes[0].action {
+        Action::Function(function) => {
+            let args = genome::args(genes).map(simplify).collect();
+            simplify_call(function, args)
+        }
+        _ => genes.to_vec(),
+    }
+}
+
+//simplifies `function` applied to arguments that are already simplified
+fn simplify_call(function: Function, args: Vec<Genome>) -> Genome {
+    let primitive = match function {
+        Function::Builtin(primitive) => primitive,
+        Function::Custom(_) => return genome::node(Action::Function(function), args),
+    };
+    let constants: Option<Vec<f64>> = args.iter().map(|arg| constant(arg)).collect();
+    if let Some(constants) = constants {
+        let value = primitive.apply(&constants);
+        //keep NaN and infinities as the expression that produces them
+        if value.is_finite() {
+            return leaf(value);
+        }
+    }
+    if args.len() != 2 {
+        return genome::node(Action::Function(function), args);
+    }
+
+    let (a, b) = (&args[0], &args[1]);
+    let (a_value, b_value) = (constant(a), constant(b));
+    match primitive {
+        Primitive::Add if a_value == Some(0.0) => return b.clone(),
+        Primitive::Add | Primitive::Sub if b_value == Some(0.0) => return a.clone(),
+        Primitive::Sub if a == b => return leaf(0.0),
+        Primitive::Mul if a_value == Some(0.0) || b_value == Some(0.0) => return leaf(0.0),
+        Primitive::Mul if a_value == Some(1.0) => return b.clone(),
+        Primitive::Mul | Primitive::Div if b_value == Some(1.0) => return a.clone(),
+        Primitive::Div if a_value == Some(0.0) => return leaf(0.0),
+        Primitive::Div if a == b => return leaf(1.0),
+        Primitive::Exp if b_value == Some(1.0) => return a.clone(),
+        Primitive::Exp if b_value == Some(0.0) => return leaf(1.0),
+        Primitive::Min | Primitive::Max if a == b => return a.clone(),
+        _ => {}
+    }
+
+    //collapse a constant into a nested constant: (x + 2) - 3 becomes x + -1, 2 * (3 * x) becomes
+    //x * 6
+    let (collapsed, nested) = match primitive {
+        Primitive::Add => (
+            Primitive::Add,
+            offset(a).zip(b_value).or_else(|| offset(b).zip(a_value)),
+        ),
+        Primitive::Sub => (Primitive::Add, offset(a).zip(b_value.map(|c| -c))),
+        Primitive::Mul => (
+            Primitive::Mul,
+            scale(a).zip(b_value).or_else(|| scale(b).zip(a_value)),
+        ),
+        _ => return genome::node(Action::Function(function), args),
+    };
+    if let Some(((x, inner), outer)) = nested {
+        let value = collapsed.apply(&[inner, outer]);
+        if value.is_finite() {
+            return simplify_call(Function::Builtin(collapsed), vec![x.to_vec(), leaf(value)]);
+        }
+    }
+    genome::node(Action::Function(function), args)
+}
+
+fn constant(genes: &[Gene]) -> Option<f64> {
+    match genes[0].action {
+        Action::Terminal(value) => Some(value),
+        _ => None,
+    }
+}
+
+//`genes` as x + c, for x + c, c + x and x - c
+fn offset(genes: &[Gene]) -> Option<(&[Gene], f64)> {
+    let (primitive, a, b) = binary(genes)?;
+    match (primitive, constant(a), constant(b)) {
+        (Primitive::Add, None, Some(c)) => Some((a, c)),
+        (Primitive::Add, Some(c), None) => Some((b, c)),
+        (Primitive::Sub, None, Some(c)) => Some((a, -c)),
+        _ => None,
+    }
+}
+
+//`genes` as x * c, for x * c and c * x
+fn scale(genes: &[Gene]) -> Option<(&[Gene], f64)> {
+    let (primitive, a, b) = binary(genes)?;
+    match (primitive, constant(a), constant(b)) {
+        (Primitive::Mul, None, Some(c)) => Some((a, c)),
+        (Primitive::Mul, Some(c), None) => Some((b, c)),
+        _ => None,
+    }
+}
+
+fn binary(genes: &[Gene]) -> Option<(Primitive, &[Gene], &[Gene])> {
+    match genes[0].action {
+        Action::Function(Function::Builtin(primitive)) if primitive.arity() == 2 => {
+            let mut args = genome::args(genes);
+            Some((primitive, args.next()?, args.next()?))
+        }
+        _ => None,
+    }
+}
+
+fn leaf(value: f64) -> Genome {
+    vec![Gene::leaf(Action::Terminal(value))]
+}
+
+impl GP {
+    /// Replaces members by their simplified form when it is smaller and measures the same
+    /// fitness. Each simplified member costs one fitness evaluation.
+    pub(crate) fn simplify_population(&mut self) {
+        let mut edits: Vec<(usize, Genome)> = self
+            .population
+            .iter()
+            .enumerate()
+            .map(|(index, member)| (index, simplify(&member.chromosome)))
+            .filter(|(index, simplified)| {
+                simplified.len() < self.population[*index].chromosome.len()
+            })
+            .collect();
+        edits.truncate(self.remaining_fitness_evals());
+        let chromosomes: Vec<&Genome> = edits.iter().map(|(_, simplified)| simplified).collect();
+        let measurements = self.measure_fitness_batch(&chromosomes);
+        let mut simplified_count = 0;
+        for ((index, simplified), measurement) in edits.into_iter().zip(measurements) {
+            let member = &mut self.population[index];
+            //both sides went through FitnessMetric::fitness, so there is no NaN to compare
+            if measurement.fitness == member.fitness {
+                *member = Member::measured(simplified, measurement);
+                simplified_count += 1;
+            }
+        }
+        log!(
+            self,
+            Debug,
+            "gen {}: simplified {} members",
+            self.gen,
+            simplified_count
+        );
+    }
+}
@@ -0,0 +1,340 @@
+//! Saving a whole run and continuing it later, see `GP::save_state`.
+//!
+//! The state is JSON with a `version` field. Chromosomes are written as prefix-ordered tokens,
+//! `f<index>` for an entry of the function set, `v<index>` for a dataset column and `c<value>`
+//! for a constant, and non-finite numbers as the strings `"NaN"`, `"inf"` and `"-inf"`, so
+//! every value reads back exactly.
+
+use crate::genome::{Gene, Genome};
+use crate::hall_of_fame::Fame;
+use crate::primitive::{CustomPrimitive, Kernel, Primitive};
+use crate::{utils, Action, Config, Dataset, GenerationStats, Member, StopReason, GP};
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+/// Bumped whenever the saved fields change, states of other versions are rejected.
+const STATE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct State {
+    version: u32,
+    //registered primitives are not part of it, they are restored from `custom_primitives`
+    config: Config,
+    columns: Vec<String>,
+    //the dataset's rows, as `Dataset::from_values` takes them
+    values: Vec<Float>,
+    custom_primitives: Vec<SavedPrimitive>,
+    seed: u64,
+    rng: Pcg32,
+    fitness_evaluations: usize,
+    gen: u16,
+    population: Vec<SavedMember>,
+    hall_of_fame: Vec<SavedFame>,
+    history: Vec<GenerationStats>,
+    stop_reason: Option<StopReason>,
+    //time the run has been going, not counting pauses
+    elapsed_ms: f64,
+    paused: bool,
+    best_fitness: Float,
+    last_improvement_gen: u16,
+    duplicates_rejected: usize,
+    seed_chromosomes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedPrimitive {
+    name: String,
+    arity: usize,
+    template: Option<String>,
+    //the built-in kernel, `None` for a JS function that has to be passed to `load_state`
+    kernel: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedMember {
+    chromosome: String,
+    fitness: Float,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedFame {
+    chromosome: String,
+    fitness: Float,
+    expression: String,
+    gen: u16,
+}
+
+//an f64 that survives JSON, which has no NaN or infinities
+#[derive(Clone, Copy)]
+struct Float(f64);
+
+impl Serialize for Float {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.0.is_finite() {
+            serializer.serialize_f64(self.0)
+        } else {
+            serializer.serialize_str(&self.0.to_string())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Float {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Number {
+            Finite(f64),
+            Text(String),
+        }
+        match Number::deserialize(deserializer)? {
+            Number::Finite(value) => Ok(Float(value)),
+            Number::Text(text) => text
+                .parse()
+                .map(Float)
+                .map_err(|_| serde::de::Error::custom(format!("invalid number {}", text))),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl GP {
+    /// Everything needed to continue this run: config, dataset, population, random number
+    /// generator and progress, as versioned JSON. Log callbacks are not saved.
+    pub fn save_state(&self) -> String {
+        let elapsed_ms = self.paused_at.unwrap_or_else(utils::now_ms) - self.started_at;
+        let state = State {
+            version: STATE_VERSION,
+            config: self.config.clone(),
+            columns: self.dataset.columns(),
+            values: self.dataset.values().into_iter().map(Float).collect(),
+            custom_primitives: self
+                .custom_primitives
+                .iter()
+                .map(|primitive| SavedPrimitive {
+                    name: primitive.name.clone(),
+                    arity: primitive.arity,
+                    template: primitive.template.clone(),
+                    kernel: match primitive.kernel {
+                        Kernel::Builtin(kernel) => Some(kernel.name().to_string()),
+                        Kernel::Js(_) => None,
+                    },
+                })
+                .collect(),
+            seed: self.seed,
+            rng: self.rng.clone(),
+            fitness_evaluations: self.fitness_evaluations,
+            gen: self.gen,
+            population: self
+                .population
+                .iter()
+                .map(|member| SavedMember {
+                    chromosome: self.encode_chromosome(&member.chromosome),
+                    fitness: Float(member.fitness),
+                })
+                .collect(),
+            hall_of_fame: self
+                .hall_of_fame
+                .iter()
+                .map(|fame| SavedFame {
+                    chromosome: self.encode_chromosome(&fame.member.chromosome),
+                    fitness: Float(fame.member.fitness),
+                    expression: fame.expression.clone(),
+                    gen: fame.gen,
+                })
+                .collect(),
+            history: self.history.clone(),
+            stop_reason: self.stop_reason,
+            elapsed_ms: if self.population.is_empty() {
+                0.0
+            } else {
+                elapsed_ms
+            },
+            paused: self.paused_at.is_some(),
+            best_fitness: Float(self.best_fitness),
+            last_improvement_gen: self.last_improvement_gen,
+            duplicates_rejected: self.duplicates_rejected,
+            seed_chromosomes: self
+                .seed_chromosomes
+                .iter()
+                .map(|chromosome| self.encode_chromosome(chromosome))
+                .collect(),
+        };
+        serde_json::to_string(&state).unwrap()
+    }
+
+    /// Continues a run saved with `save_state`: with a fixed seed, the next `tick` gives the
+    /// same result the saved run would have. JS primitives cannot be saved, so `js_primitives`
+    /// maps each of their names to its function again.
+    pub fn load_state(state: &str, js_primitives: Option<js_sys::Object>) -> Result<GP, JsValue> {
+        GP::restore(state, |name| {
+            let function = js_sys::Reflect::get(js_primitives.as_ref()?, &JsValue::from_str(name));
+            function.ok()?.dyn_into::<js_sys::Function>().ok()
+        })
+        .map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+impl GP {
+    /// The Rust equivalent of `load_state`, for runs without JS primitives.
+    pub fn restore_state(state: &str) -> Result<GP, String> {
+        GP::restore(state, |_| None)
+    }
+
+    fn restore(
+        state: &str,
+        js_primitive: impl Fn(&str) -> Option<js_sys::Function>,
+    ) -> Result<GP, String> {
+        let json: serde_json::Value =
+            serde_json::from_str(state).map_err(|e| format!("invalid state: {}", e))?;
+        let version = json.get("version").and_then(serde_json::Value::as_u64);
+        if version != Some(STATE_VERSION as u64) {
+            return Err(format!(
+                "unsupported state version {}, expected {}",
+                json.get("version").unwrap_or(&serde_json::Value::Null),
+                STATE_VERSION
+            ));
+        }
+        let state: State =
+            serde_json::from_value(json).map_err(|e| format!("invalid state: {}", e))?;
+
+        let values = state.values.iter().map(|value| value.0).collect();
+        let dataset = Dataset::from_values(state.columns, values)?;
+        let mut gp = GP::from_dataset(dataset, state.config)?;
+        for primitive in state.custom_primitives {
+            let kernel = match &primitive.kernel {
+                Some(kernel) => Kernel::Builtin(
+                    Primitive::from_name(kernel)
+                        .ok_or_else(|| format!("unknown kernel: {}", kernel))?,
+                ),
+                None => Kernel::Js(js_primitive(&primitive.name).ok_or_else(|| {
+                    format!(
+                        "the state uses the JS primitive {}, pass its function to load_state",
+                        primitive.name
+                    )
+                })?),
+            };
+            gp.add_custom_primitive(CustomPrimitive {
+                name: primitive.name,
+                arity: primitive.arity,
+                template: primitive.template,
+                kernel,
+            })?;
+        }
+
+        let population = state
+            .population
+            .iter()
+            .map(|member| gp.restore_member(&member.chromosome, member.fitness))
+            .collect::<Result<_, String>>()?;
+        let hall_of_fame = state
+            .hall_of_fame
+            .iter()
+            .map(|fame| {
+                Ok(Fame {
+                    member: gp.restore_member(&fame.chromosome, fame.fitness)?,
+                    expression: fame.expression.clone(),
+                    gen: fame.gen,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        let seed_chromosomes = state
+            .seed_chromosomes
+            .iter()
+            .map(|chromosome| gp.decode_chromosome(chromosome))
+            .collect::<Result<_, String>>()?;
+
+        let now = utils::now_ms();
+        gp.seed = state.seed;
+        gp.rng = state.rng;
+        gp.fitness_evaluations = state.fitness_evaluations;
+        gp.gen = state.gen;
+        gp.population = population;
+        gp.hall_of_fame = hall_of_fame;
+        gp.history = state.history;
+        gp.stop_reason = state.stop_reason;
+        gp.started_at = now - state.elapsed_ms;
+        gp.paused_at = if state.paused { Some(now) } else { None };
+        gp.best_fitness = state.best_fitness.0;
+        gp.last_improvement_gen = state.last_improvement_gen;
+        gp.duplicates_rejected = state.duplicates_rejected;
+        gp.seed_chromosomes = seed_chromosomes;
+        Ok(gp)
+    }
+
+    //the dedup signature is not saved, it is taken from the member's outputs again
+    fn restore_member(&self, chromosome: &str, fitness: Float) -> Result<Member, String> {
+        let chromosome = self.decode_chromosome(chromosome)?;
+        let signature = self.dedup.signature(&self.predict(&chromosome));
+        Ok(Member {
+            chromosome,
+            fitness: fitness.0,
+            signature,
+        })
+    }
+
+    fn encode_chromosome(&self, chromosome: &[Gene]) -> String {
+        chromosome
+            .iter()
+            .map(|gene| match gene.action {
+                Action::Function(function) => {
+                    let index = self.functions.iter().position(|f| *f == function).unwrap();
+                    format!("f{}", index)
+                }
+                Action::Var(index) => format!("v{}", index),
+                Action::Terminal(value) => format!("c{}", value),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn decode_chromosome(&self, text: &str) -> Result<Genome, String> {
+        let column_count = self.dataset.columns().len();
+        let actions = text
+            .split(' ')
+            .map(|token| {
+                let (kind, value) = token.split_at(token.len().min(1));
+                let action = match kind {
+                    "f" => value
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|index| self.functions.get(index))
+                        .map(|function| Action::Function(*function)),
+                    "v" => value
+                        .parse()
+                        .ok()
+                        .filter(|index| *index < column_count)
+                        .map(Action::Var),
+                    "c" => value.parse().ok().map(Action::Terminal),
+                    _ => None,
+                };
+                action.ok_or_else(|| format!("invalid gene {:?} in {:?}", token, text))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut chromosome = Vec::with_capacity(actions.len());
+        if !self.decode_subtree(&actions, &mut chromosome) || chromosome.len() != actions.len() {
+            return Err(format!("malformed chromosome {:?}", text));
+        }
+        Ok(chromosome)
+    }
+
+    //appends the subtree starting at the next action, false if the actions run out first
+    fn decode_subtree(&self, actions: &[Action], chromosome: &mut Genome) -> bool {
+        let index = chromosome.len();
+        let action = match actions.get(index) {
+            Some(action) => *action,
+            None => return false,
+        };
+        chromosome.push(Gene::leaf(action));
+        if let Action::Function(function) = action {
+            for _ in 0..self.function_arity(function) {
+                if !self.decode_subtree(actions, chromosome) {
+                    return false;
+                }
+            }
+        }
+        chromosome[index].size = chromosome.len() - index;
+        true
+    }
+}
@@ -0,0 +1,106 @@
+use crate::genome;
+use crate::{Objective, GP};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// A summary of the population at the end of one generation, generation 0 being the
+/// population built by `GP::init`.
+///
+/// Fitness figures only consider members with a finite fitness, `non_finite` counts the rest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationStats {
+    pub gen: u16,
+    pub best_fitness: Option<f64>,
+    pub worst_fitness: Option<f64>,
+    pub mean_fitness: Option<f64>,
+    pub median_fitness: Option<f64>,
+    pub std_dev_fitness: Option<f64>,
+    pub non_finite: usize,
+    pub mean_size: f64,
+    pub max_size: usize,
+    pub mean_depth: f64,
+    pub max_depth: u16,
+    pub unique: usize,
+    //rejected by `Config::dedup` while building this generation
+    pub duplicates_rejected: usize,
+    pub fitness_evaluations: usize,
+}
+
+impl GP {
+    pub(crate) fn record_generation_stats(&mut self) {
+        let mut fitness: Vec<f64> = self
+            .population
+            .iter()
+            .map(|member| member.fitness)
+            .filter(|fitness| fitness.is_finite())
+            .collect();
+        fitness.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let (mean_fitness, median_fitness, std_dev_fitness) = if fitness.is_empty() {
+            (None, None, None)
+        } else {
+            let count = fitness.len() as f64;
+            let mean = fitness.iter().sum::<f64>() / count;
+            let middle = fitness.len() / 2;
+            let median = if fitness.len().is_multiple_of(2) {
+                (fitness[middle - 1] + fitness[middle]) / 2.0
+            } else {
+                fitness[middle]
+            };
+            let variance = fitness.iter().map(|f| (f - mean).powi(2)).sum::<f64>() / count;
+            (Some(mean), Some(median), Some(variance.sqrt()))
+        };
+
+        let sizes: Vec<usize> = self
+            .population
+            .iter()
+            .map(|member| member.chromosome.len())
+            .collect();
+        let depths: Vec<u16> = self
+            .population
+            .iter()
+            .map(|member| genome::depth(&member.chromosome))
+            .collect();
+        let unique: HashSet<String> = self
+            .population
+            .iter()
+            .map(|member| self.chromosome_to_string(&member.chromosome))
+            .collect();
+        let population_size = self.population.len() as f64;
+        let (lowest, highest) = (fitness.first().cloned(), fitness.last().cloned());
+        let (best_fitness, worst_fitness) = match self.metric.objective() {
+            Objective::Minimize => (lowest, highest),
+            Objective::Maximize => (highest, lowest),
+        };
+
+        let stats = GenerationStats {
+            gen: self.gen,
+            best_fitness,
+            worst_fitness,
+            mean_fitness,
+            median_fitness,
+            std_dev_fitness,
+            non_finite: self.population.len() - fitness.len(),
+            mean_size: sizes.iter().sum::<usize>() as f64 / population_size,
+            max_size: sizes.iter().cloned().max().unwrap_or(0),
+            mean_depth: depths.iter().map(|depth| *depth as f64).sum::<f64>() / population_size,
+            max_depth: depths.iter().cloned().max().unwrap_or(0),
+            unique: unique.len(),
+            duplicates_rejected: self.duplicates_rejected,
+            fitness_evaluations: self.fitness_evaluations,
+        };
+        log!(
+            self,
+            Debug,
+            "gen {}: best {:?}, mean {:?}, mean size {:.1}, {} unique, {} duplicates rejected",
+            stats.gen,
+            stats.best_fitness,
+            stats.mean_fitness,
+            stats.mean_size,
+            stats.unique,
+            stats.duplicates_rejected
+        );
+        self.history.push(stats);
+        self.duplicates_rejected = 0;
+    }
+}
@@ -0,0 +1,24 @@
+//! Fixtures shared by the integration tests. Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use wasm_genetic_programming::{Config, Dataset, GP};
+
+//3x^3 + 2x^2 + x + 1, the first example in the web front end
+pub fn cubic() -> Vec<f64> {
+    (-10..=10)
+        .flat_map(|i| {
+            let x = i as f64 / 2.0;
+            vec![x, 3.0 * x * x * x + 2.0 * x * x + x + 1.0]
+        })
+        .collect()
+}
+
+//one row of ones, with 2 in the first column, so constant expressions are told apart from it
+pub fn gp_with_columns(columns: &[&str], functions: &[&str]) -> GP {
+    let mut config = Config::new();
+    config.set_chromosome_function(functions.iter().map(|name| name.to_string()).collect());
+    let mut values = vec![1.0; columns.len() + 1];
+    values[0] = 2.0;
+    let columns = columns.iter().map(|column| column.to_string()).collect();
+    GP::from_dataset(Dataset::from_values(columns, values).unwrap(), config).unwrap()
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use wasm_genetic_programming::*;
+
+const FUNCTIONS: [&str; 7] = ["+", "-", "*", "/", "exp", "log", "min"];
+
+fn gp_with_columns(columns: &[&str]) -> GP {
+    common::gp_with_columns(columns, &FUNCTIONS)
+}
+
+#[test]
+fn every_language_computes_the_same_expression() {
+    let gp = gp_with_columns(&["x", "y"]);
+    let code = |language: &str| {
+        gp.generate_code(language, Some("exp(x, 2) / min(log(y), 1)"))
+            .unwrap()
+    };
+    assert_eq!(
+        code("rust"),
+        "/// (exp(x, 2) / min(log(y), 1))\n\
+         pub fn model(x: f64, y: f64) -> f64 {\n    \
+         (f64::powf(x, 2.0) / f64::min(f64::ln(y), 1.0))\n}\n"
+    );
+    assert_eq!(
+        code("js"),
+        "// (exp(x, 2) / min(log(y), 1))\n\
+         function model(x, y) {\n  \
+         const pow = (a, b) =>\n    \
+         a === 1 || (a === -1 && Math.abs(b) === Infinity) ? 1 : Math.pow(a, b);\n  \
+         const fmin = (a, b) => (isNaN(a) ? b : isNaN(b) ? a : Math.min(a, b));\n  \
+         return (pow(x, 2.0) / fmin(Math.log(y), 1.0));\n}\n"
+    );
+    assert_eq!(
+        code("python"),
+        "import numpy as np\n\n\n\
+         def model(x, y):\n    \
+         \"\"\"(exp(x, 2) / min(log(y), 1))\"\"\"\n    \
+         x = np.asarray(x, dtype=np.float64)\n    \
+         y = np.asarray(y, dtype=np.float64)\n    \
+         with np.errstate(all=\"ignore\"):\n        \
+         return np.divide(np.power(x, 2.0), np.fmin(np.log(y), 1.0))\n"
+    );
+    assert_eq!(
+        code("c"),
+        "#include <math.h>\n\n\
+         /* (exp(x, 2) / min(log(y), 1)) */\n\
+         double model(double x, double y) {\n    \
+         return (pow(x, 2.0) / fmin(log(y), 1.0));\n}\n"
+    );
+    assert_eq!(
+        gp.generate_code("fortran", None).unwrap_err(),
+        "unknown language: fortran, expected rust, js, python or c"
+    );
+}
+
+#[test]
+fn columns_become_valid_parameter_names() {
+    let gp = gp_with_columns(&["class", "größe", "np"]);
+    let python = gp.generate_code("python", Some("class * 2")).unwrap();
+    assert!(python.contains("def model(class_, gr__e, np_):"));
+    assert!(python.contains("return (class_ * 2.0)"));
+    let rust = gp.generate_code("rust", Some("class * 2")).unwrap();
+    assert!(rust.contains("pub fn model(class: f64, _gr__e: f64, _np: f64) -> f64"));
+}
+
+#[test]
+fn constants_keep_their_exact_value() {
+    let gp = gp_with_columns(&["x"]);
+    let code = |language: &str, expression: &str| {
+        let code = gp.generate_code(language, Some(expression)).unwrap();
+        code.lines()
+            .find(|line| line.trim_start().starts_with("return"))
+            .unwrap()
+            .trim()
+            .to_string()
+    };
+    assert_eq!(code("c", "x * 0.1 + 1e-20"), "return ((x * 0.1) + 1e-20);");
+    assert_eq!(code("js", "x - -inf"), "return (x - -Infinity);");
+    assert_eq!(code("python", "3"), "return np.full(np.shape(x), 3.0)");
+    assert_eq!(code("c", "NaN"), "return NAN;");
+}
+
+#[test]
+fn python_divides_constants_by_zero() {
+    let gp = gp_with_columns(&["x"]);
+    let python = |expression: &str| gp.generate_code("python", Some(expression)).unwrap();
+    assert!(python("x + 1 / (2 - 2)").contains("return (x + np.divide(1.0, (2.0 - 2.0)))"));
+    assert!(
+        python("1 / (2 - 2)").contains("return np.full(np.shape(x), np.divide(1.0, (2.0 - 2.0)))")
+    );
+}
+
+#[test]
+fn best_member_is_exported_by_default() {
+    let mut gp = gp_with_columns(&["x"]);
+    assert!(gp.generate_code("c", None).is_err());
+    gp.init();
+    let best = gp.step().unwrap().best;
+    let code = gp.generate_code("rust", None).unwrap();
+    assert!(code.starts_with(&format!("/// {}\n", best)));
+}
@@ -0,0 +1,87 @@
+use wasm_genetic_programming::*;
+
+const TARGETS: [f64; 4] = [1.0, 2.0, 3.0, 4.0];
+const PREDICTIONS: [f64; 4] = [1.0, 2.5, 3.0, 6.0];
+
+#[test]
+fn error_metrics() {
+    let score = |metric: FitnessMetric| metric.evaluate(&PREDICTIONS, &TARGETS);
+    assert_eq!(score(FitnessMetric::Sae), 2.5);
+    assert_eq!(score(FitnessMetric::Mae), 0.625);
+    assert_eq!(score(FitnessMetric::Mse), 1.0625);
+    assert_eq!(score(FitnessMetric::Rmse), 1.0625f64.sqrt());
+    assert_eq!(score(FitnessMetric::MaxError), 2.0);
+    assert_eq!(score(FitnessMetric::Huber { delta: 1.0 }), 0.40625);
+    assert_eq!(score(FitnessMetric::Hits { tolerance: 0.5 }), 3.0);
+    //the targets have variance 1.25
+    assert_eq!(score(FitnessMetric::R2), 1.0 - 4.25 / 5.0);
+    assert_eq!(
+        score(FitnessMetric::Nrmse),
+        1.0625f64.sqrt() / 1.25f64.sqrt()
+    );
+}
+
+#[test]
+fn nan_propagates() {
+    let predictions = [1.0, f64::NAN, 3.0, 4.0];
+    for metric in &[
+        FitnessMetric::Sae,
+        FitnessMetric::Rmse,
+        FitnessMetric::MaxError,
+        FitnessMetric::R2,
+    ] {
+        assert!(metric.evaluate(&predictions, &TARGETS).is_nan());
+    }
+}
+
+#[test]
+fn sorting_leaves_the_best_last_and_nan_first() {
+    let fitness = [2.0, f64::NAN, 5.0, f64::NEG_INFINITY, 1.0];
+    let mut minimized = fitness.to_vec();
+    minimized.sort_by(|a, b| Objective::Minimize.compare(*a, *b));
+    assert!(minimized[0].is_nan());
+    assert_eq!(&minimized[1..], &[5.0, 2.0, 1.0, f64::NEG_INFINITY]);
+    let mut maximized = fitness.to_vec();
+    maximized.sort_by(|a, b| Objective::Maximize.compare(*a, *b));
+    assert!(maximized[0].is_nan());
+    assert_eq!(&maximized[1..], &[f64::NEG_INFINITY, 1.0, 2.0, 5.0]);
+}
+
+#[test]
+fn objective_follows_the_metric() {
+    let mut config = Config::new();
+    config.set_pop_size(50);
+    config.set_seed(Some(1));
+    config.set_fitness_metric("hits".to_string());
+    config.set_hit_tolerance(5.0);
+    let values: Vec<f64> = (0..10)
+        .flat_map(|i| vec![i as f64, i as f64 * 3.0])
+        .collect();
+    let mut gp = GP::with_config(values, &config).unwrap();
+    assert_eq!(gp.objective(), Objective::Maximize);
+    gp.init();
+    let stats = &gp.generation_stats()[0];
+    assert!(stats.best_fitness >= stats.worst_fitness);
+    assert_eq!(
+        gp.step().unwrap().fitness,
+        gp.generation_stats()[1].best_fitness
+    );
+
+    config.set_fitness_metric("cosine".to_string());
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn run_stops_at_target_in_the_chosen_metric() {
+    let values: Vec<f64> = (0..10).flat_map(|i| vec![i as f64, i as f64]).collect();
+    let mut config = Config::new();
+    config.set_pop_size(100);
+    config.set_seed(Some(1));
+    config.set_fitness_metric("hits".to_string());
+    config.set_target_fitness(Some(1.0));
+    let mut gp = GP::with_config(values, &config).unwrap();
+    gp.init();
+    let result = gp.step().unwrap();
+    assert!(result.done);
+    assert_eq!(result.stop_reason, Some("target_fitness"));
+}
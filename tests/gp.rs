@@ -0,0 +1,583 @@
+mod common;
+
+use common::cubic;
+use wasm_genetic_programming::*;
+
+fn seeded_gp(seed: u64) -> GP {
+    let mut config = Config::new();
+    config.set_pop_size(200);
+    config.set_seed(Some(seed));
+    GP::with_config(cubic(), &config).unwrap()
+}
+
+#[test]
+fn same_seed_repeats_the_run() {
+    let mut a = seeded_gp(42);
+    let mut b = seeded_gp(42);
+    a.init();
+    b.init();
+    for _ in 0..5 {
+        let (a, b) = (a.step().unwrap(), b.step().unwrap());
+        assert_eq!(a.best, b.best);
+        assert_eq!(a.fitness, b.fitness);
+        assert_eq!(a.seed, "42");
+    }
+}
+
+#[test]
+fn tick_result_is_plain_json() {
+    let mut gp = seeded_gp(7);
+    gp.init();
+    let result = gp.step().unwrap();
+    let json: serde_json::Value =
+        serde_json::from_str(&serde_json::to_string(&result).unwrap()).unwrap();
+    assert_eq!(json["gen"], 1);
+    assert_eq!(json["done"], false);
+    assert_eq!(json["best"], result.best.as_str());
+    assert!(json["chromosome"]["action"].is_string() || json["chromosome"]["action"].is_number());
+}
+
+#[test]
+fn budget_stops_the_run_mid_generation() {
+    let mut config = Config::new();
+    config.set_pop_size(200);
+    config.set_seed(Some(3));
+    config.set_max_fitness_evals(210);
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    gp.init();
+    let result = gp.step().unwrap();
+    assert!(result.done);
+    assert_eq!(result.gen, 1);
+    assert_eq!(result.stop_reason, Some("max_fitness_evals"));
+    assert_eq!(result.fitness_evaluations, 210);
+    //the generation is filled up from the previous one
+    assert_eq!(gp.population_entries().len(), 200);
+    assert_eq!(gp.step().unwrap().fitness_evaluations, 210);
+
+    //a budget smaller than the population stops during init
+    config.set_max_fitness_evals(150);
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    gp.init();
+    assert_eq!(gp.population_entries().len(), 150);
+    let result = gp.step().unwrap();
+    assert_eq!(result.stop_reason, Some("max_fitness_evals"));
+    assert_eq!(result.fitness_evaluations, 150);
+}
+
+#[test]
+fn time_limit_stops_the_run() {
+    let mut config = Config::new();
+    config.set_pop_size(50);
+    config.set_seed(Some(3));
+    config.set_max_time_ms(Some(1.0));
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    gp.init();
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    let result = gp.step().unwrap();
+    assert!(result.done);
+    assert_eq!(result.stop_reason, Some("max_time"));
+}
+
+#[test]
+fn stagnation_limit_stops_after_generations_without_improvement() {
+    let mut config = Config::new();
+    config.set_pop_size(50);
+    config.set_seed(Some(3));
+    config.set_stagnation_limit(Some(3));
+    //unreachable, so only stagnation can stop the run
+    config.set_target_fitness(Some(-1.0));
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    //an exact answer from the start, which nothing can improve on
+    gp.add_seed_expression("3 * x * x * x + 2 * x * x + x + 1")
+        .unwrap();
+    gp.init();
+    for gen in 1..=3 {
+        let result = gp.step().unwrap();
+        assert!(!result.done, "stopped at generation {}", gen);
+    }
+    let result = gp.step().unwrap();
+    assert!(result.done);
+    assert_eq!(result.gen, 3);
+    assert_eq!(result.stop_reason, Some("stagnation"));
+}
+
+#[test]
+fn finished_runs_stay_at_their_last_generation() {
+    let mut config = Config::new();
+    config.set_pop_size(50);
+    config.set_seed(Some(5));
+    config.set_max_generations(2);
+    config.set_target_fitness(Some(-1.0));
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    gp.init();
+    assert!(!gp.step().unwrap().done);
+    assert!(!gp.step().unwrap().done);
+    let result = gp.step().unwrap();
+    assert!(result.done);
+    assert_eq!(result.stop_reason, Some("max_generations"));
+    assert_eq!(result.gen, 2);
+    for _ in 0..2 {
+        let result = gp.step().unwrap();
+        assert!(result.done);
+        assert_eq!(result.gen, 2);
+    }
+    assert_eq!(gp.generation_stats().last().unwrap().gen, 2);
+}
+
+#[test]
+fn history_has_one_entry_per_generation() {
+    let mut gp = seeded_gp(3);
+    gp.init();
+    for _ in 0..3 {
+        gp.step().unwrap();
+    }
+    let history = gp.generation_stats();
+    assert_eq!(history.len(), 4);
+    for (gen, stats) in history.iter().enumerate() {
+        assert_eq!(stats.gen as usize, gen);
+        assert!(stats.best_fitness <= stats.median_fitness);
+        assert!(stats.median_fitness <= stats.worst_fitness);
+        assert!(stats.unique > 0 && stats.unique <= 200);
+        assert!(stats.max_size as f64 >= stats.mean_size);
+    }
+}
+
+#[test]
+fn dataset_columns_become_terminals() {
+    //y = a * b + c
+    let mut values = Vec::new();
+    for a in 0..4 {
+        for b in 0..4 {
+            let (a, b, c) = (a as f64, b as f64, (a + b) as f64 / 2.0);
+            values.extend_from_slice(&[a, b, c, a * b + c]);
+        }
+    }
+    let columns = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+    let dataset = Dataset::from_values(columns, values).unwrap();
+    assert_eq!(dataset.rows(), 16);
+    assert_eq!(dataset.terminal_set(), vec!["a", "b", "c", "R"]);
+
+    let mut config = Config::new();
+    config.set_pop_size(100);
+    config.set_seed(Some(1));
+    let mut gp = GP::from_dataset(dataset, config).unwrap();
+    assert_eq!(gp.config().chromosome_terminal(), vec!["a", "b", "c", "R"]);
+    gp.init();
+    let best = gp.step().unwrap().best;
+    assert!(!best.contains('x'));
+}
+
+#[test]
+fn unknown_terminal_is_rejected() {
+    let dataset = Dataset::from_values(vec!["a".to_string()], vec![1.0, 2.0]).unwrap();
+    let mut config = Config::new();
+    config.set_chromosome_terminal(vec!["x".to_string(), "R".to_string()]);
+    assert!(GP::from_dataset(dataset, config).is_err());
+}
+
+#[test]
+fn unknown_function_is_rejected() {
+    let mut config = Config::new();
+    config.set_chromosome_function(vec!["+".to_string(), "gamma".to_string()]);
+    assert_eq!(
+        config.validate(),
+        Err("unknown function: gamma".to_string())
+    );
+}
+
+#[test]
+fn registered_primitive_joins_the_function_set() {
+    let mut config = Config::new();
+    config.set_pop_size(100);
+    config.set_seed(Some(9));
+    config.set_chromosome_function(vec!["+".to_string()]);
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    gp.register_primitive("root".to_string(), 1, Some("√{0}".to_string()), "sqrt")
+        .unwrap();
+    //the config only names built-in primitives, so it can start another run
+    assert_eq!(gp.config().chromosome_function(), vec!["+"]);
+    assert!(GP::with_config(cubic(), &gp.config()).is_ok());
+    gp.init();
+    for _ in 0..3 {
+        let result = gp.step().unwrap();
+        assert!(!result.best.contains("sqrt"));
+    }
+}
+
+#[test]
+fn population_keeps_its_size() {
+    //an odd number of free slots leaves room for only one member of the last crossover
+    for pop_size in &[100, 101] {
+        let mut config = Config::new();
+        config.set_pop_size(*pop_size);
+        config.set_seed(Some(9));
+        let mut gp = GP::with_config(cubic(), &config).unwrap();
+        gp.init();
+        for _ in 0..30 {
+            gp.step().unwrap();
+            assert_eq!(gp.population_entries().len(), *pop_size as usize);
+        }
+    }
+}
+
+#[test]
+fn elites_keep_the_best_fitness_from_getting_worse() {
+    let mut config = Config::new();
+    config.set_pop_size(100);
+    config.set_seed(Some(11));
+    //random selection gives no pressure at all, only the elite holds on to the best
+    config.set_selection("random".to_string());
+    config.set_elite_count(2);
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    gp.init();
+    let mut best = gp.generation_stats()[0].best_fitness.unwrap();
+    for _ in 0..10 {
+        let fitness = gp.step().unwrap().fitness.unwrap();
+        assert!(fitness <= best);
+        best = fitness;
+    }
+}
+
+#[test]
+fn hall_of_fame_holds_the_best_distinct_expressions() {
+    let mut config = Config::new();
+    config.set_pop_size(200);
+    config.set_seed(Some(5));
+    config.set_hall_of_fame_size(5);
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    gp.init();
+    for _ in 0..5 {
+        gp.step().unwrap();
+    }
+    let hall_of_fame = gp.hall_of_fame_entries();
+    assert_eq!(hall_of_fame.len(), 5);
+    assert_eq!(
+        hall_of_fame[0].fitness,
+        gp.generation_stats().last().unwrap().best_fitness
+    );
+    for pair in hall_of_fame.windows(2) {
+        assert!(pair[0].fitness <= pair[1].fitness);
+        assert_ne!(pair[0].best, pair[1].best);
+    }
+}
+
+#[test]
+fn structural_dedup_keeps_the_population_distinct() {
+    let mut config = Config::new();
+    config.set_pop_size(100);
+    config.set_seed(Some(9));
+    config.set_dedup("structural".to_string());
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    gp.init();
+    gp.step().unwrap();
+    let history = gp.generation_stats();
+    assert_eq!(history[0].unique, 100);
+    assert!(history[0].duplicates_rejected > 0);
+    assert!(history[1].duplicates_rejected > 0);
+
+    config.set_dedup("off".to_string());
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    gp.init();
+    assert_eq!(gp.generation_stats()[0].duplicates_rejected, 0);
+}
+
+#[test]
+fn semantic_dedup_rejects_equivalent_trees() {
+    let mut config = Config::new();
+    config.set_pop_size(100);
+    config.set_seed(Some(9));
+    config.set_dedup("semantic".to_string());
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    gp.init();
+    let stats = &gp.generation_stats()[0];
+    assert_eq!(stats.unique, 100);
+    assert!(stats.duplicates_rejected > 0);
+    //every tree is evaluated once, the outputs from that evaluation also tell duplicates apart
+    assert_eq!(stats.fitness_evaluations, 100 + stats.duplicates_rejected);
+
+    config.set_semantic_tolerance(-1.0);
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn populations_can_exceed_u16() {
+    let mut config = Config::new();
+    config.set_pop_size(70_000);
+    config.set_max_fitness_evals(100_000);
+    config.set_seed(Some(9));
+    config.set_tree_limit_initial(2);
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    gp.init();
+    assert_eq!(gp.population_entries().len(), 70_000);
+    assert_eq!(gp.config().pop_size(), 70_000);
+}
+
+#[test]
+fn trees_stay_within_the_running_depth_limit() {
+    let mut config = Config::new();
+    config.set_pop_size(300);
+    config.set_seed(Some(21));
+    config.set_tree_limit_running(8);
+    config.set_mutate_prob(0.3);
+    config.set_hoist_mutate_prob(0.1);
+    config.set_shrink_mutate_prob(0.1);
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    gp.init();
+    for _ in 0..8 {
+        gp.step().unwrap();
+    }
+    for stats in gp.generation_stats() {
+        assert!(stats.max_depth <= 8);
+        assert!(stats.max_size >= stats.max_depth as usize);
+    }
+}
+
+#[test]
+fn runs_must_be_started_with_init() {
+    let mut gp = seeded_gp(13);
+    assert_eq!(
+        gp.step().unwrap_err(),
+        "init must be called before running generations"
+    );
+    assert!(gp.run_for(3, None, |_| true).is_err());
+    gp.init();
+    assert_eq!(gp.step().unwrap().gen, 1);
+}
+
+#[test]
+fn run_for_advances_in_slices() {
+    let mut gp = seeded_gp(13);
+    gp.init();
+    let mut reported = Vec::new();
+    let result = gp
+        .run_for(3, None, |result| {
+            reported.push(result.gen);
+            true
+        })
+        .unwrap();
+    assert_eq!(reported, vec![1, 2, 3]);
+    assert_eq!(result.gen, 3);
+
+    //returning false from the progress callback ends the slice early
+    let result = gp.run_for(10, None, |result| result.gen < 5).unwrap();
+    assert_eq!(result.gen, 5);
+}
+
+#[test]
+fn paused_runs_do_not_advance() {
+    let mut gp = seeded_gp(13);
+    gp.init();
+    gp.pause();
+    assert!(gp.paused());
+    assert_eq!(gp.step().unwrap().gen, 0);
+    assert_eq!(gp.run_for(5, None, |_| true).unwrap().gen, 0);
+    gp.resume();
+    assert_eq!(gp.run_for(2, None, |_| true).unwrap().gen, 2);
+}
+
+#[test]
+fn cancelled_runs_are_done() {
+    let mut gp = seeded_gp(13);
+    gp.init();
+    gp.run_for(2, None, |_| true).unwrap();
+    gp.cancel();
+    let result = gp.run_for(5, None, |_| true).unwrap();
+    assert!(result.done);
+    assert_eq!(result.gen, 2);
+    assert_eq!(result.stop_reason, Some("cancelled"));
+    assert_eq!(gp.step().unwrap().gen, 2);
+}
+
+//runs in both builds, so the serial and parallel paths are held to the same recorded values
+#[test]
+fn parallel_evaluation_gives_the_serial_results() {
+    //x^3 - 2x + 1
+    let values: Vec<f64> = (-10..=10)
+        .flat_map(|i| {
+            let x = i as f64 / 2.0;
+            vec![x, x * x * x - 2.0 * x + 1.0]
+        })
+        .collect();
+    let mut config = Config::new();
+    config.set_pop_size(300);
+    config.set_seed(Some(21));
+    //semantic dedup also depends on the outputs measured on the thread pool
+    config.set_dedup("semantic".to_string());
+    config.set_chromosome_function(
+        ["+", "-", "*", "/"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+    );
+    let mut gp = GP::with_config(values, &config).unwrap();
+    gp.init();
+    let result = gp.run_for(10, None, |_| true).unwrap();
+
+    //recorded from a serial run
+    assert_eq!(result.gen, 4);
+    assert_eq!(result.best, "(((x * x) * x) - (x - ((8 / (1 + 7)) - x)))");
+    assert_eq!(result.stop_reason, Some("target_fitness"));
+    let stats: Vec<(Option<f64>, usize, usize)> = gp
+        .generation_stats()
+        .iter()
+        .map(|stats| {
+            (
+                stats.best_fitness,
+                stats.duplicates_rejected,
+                stats.fitness_evaluations,
+            )
+        })
+        .collect();
+    assert_eq!(
+        stats,
+        vec![
+            (Some(49.5714285714286), 62, 362),
+            (Some(20.64285714285713), 574, 1195),
+            (Some(2.999999999999971), 747, 2194),
+            (Some(2.999999999999971), 904, 3332),
+            (Some(0.0), 873, 4441),
+        ]
+    );
+}
+
+#[test]
+fn csv_header_names_the_columns() {
+    let csv = "a, y, b\n1, 10, 2\n3, 20, 4\n";
+    let dataset = Dataset::from_csv(csv, Some("y")).unwrap();
+    assert_eq!(dataset.columns(), vec!["a", "b"]);
+    assert_eq!(dataset.rows(), 2);
+    assert_eq!(dataset.targets(), &[10.0, 20.0]);
+    assert_eq!(dataset.column(1), &[2.0, 4.0]);
+
+    assert!(Dataset::from_csv("a,y\n1\n", None).is_err());
+    assert!(Dataset::from_csv("a,y\n1,two\n", None).is_err());
+}
+
+#[test]
+fn column_names_must_read_back_as_variables() {
+    for name in ["temp (C)", "2nd", "a-b", "", "R", "NaN", "inf"] {
+        assert_eq!(
+            Dataset::from_values(vec![name.to_string()], vec![1.0, 2.0]).unwrap_err(),
+            format!("invalid column name: {:?}", name)
+        );
+    }
+    assert!(Dataset::from_csv("temp (C),y\n1,2\n", None).is_err());
+    let dataset = Dataset::from_values(
+        vec!["_t2".to_string(), "größe".to_string()],
+        vec![1.0, 2.0, 3.0],
+    );
+    assert!(dataset.is_ok());
+}
+
+#[test]
+fn config_round_trips_through_json() {
+    let mut config =
+        Config::parse(r#"{"pop_size": 50, "chromosome_function": ["+", "sin"]}"#).unwrap();
+    assert_eq!(config.pop_size(), 50);
+    assert_eq!(config.max_generations(), Config::new().max_generations());
+    assert_eq!(config.chromosome_combined(), vec!["+", "sin"]);
+    config.set_seed(Some(4));
+    let parsed = Config::parse(&config.to_json()).unwrap();
+    assert_eq!(parsed.seed(), Some(4));
+    assert!(Config::parse(r#"{"pop_sz": 50}"#).is_err());
+}
+
+#[test]
+fn log_handler_receives_messages_up_to_the_level() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let run = |level: &str| {
+        let mut config = Config::new();
+        config.set_pop_size(50);
+        config.set_max_generations(3);
+        config.set_seed(Some(1));
+        config.set_log_level(level.to_string());
+        let mut gp = GP::with_config(cubic(), &config).unwrap();
+        let messages = Rc::new(RefCell::new(Vec::new()));
+        let sink = messages.clone();
+        gp.set_log_handler(move |level, message| {
+            sink.borrow_mut().push((level, message.to_string()))
+        });
+        gp.init();
+        gp.run_for(10, None, |_| true).unwrap();
+        gp.step().unwrap();
+        messages.take()
+    };
+
+    let info = run("info");
+    assert_eq!(info.len(), 2);
+    assert!(info.iter().all(|(level, _)| *level == LogLevel::Info));
+    assert!(info[1].1.contains("max_generations"));
+
+    let debug = run("debug");
+    assert!(debug.iter().any(|(level, _)| *level == LogLevel::Debug));
+    assert!(debug.len() > info.len());
+
+    assert!(run("off").is_empty());
+
+    let mut config = Config::new();
+    config.set_log_level("verbose".to_string());
+    assert!(config.validate().is_err());
+}
+
+//whether `expression` still has an operator applied to two numbers, like (2 * 0.5)
+fn has_constant_operation(expression: &str) -> bool {
+    expression.split('(').any(|group| {
+        let inner = group.split(')').next().unwrap();
+        let parts: Vec<&str> = inner.split(' ').collect();
+        parts.len() == 3 && parts[0].parse::<f64>().is_ok() && parts[2].parse::<f64>().is_ok()
+    })
+}
+
+#[test]
+fn simplified_best_has_no_constant_operations() {
+    let mut gp = seeded_gp(11);
+    gp.init();
+    let mut seen_a_change = false;
+    for _ in 0..10 {
+        let result = gp.step().unwrap();
+        assert!(
+            !has_constant_operation(&result.simplified),
+            "{}",
+            result.simplified
+        );
+        seen_a_change |= result.simplified != result.best;
+    }
+    assert!(seen_a_change);
+}
+
+#[test]
+fn simplify_interval_edits_the_population() {
+    let run = |simplify_interval: Option<u16>| {
+        let mut config = Config::new();
+        config.set_pop_size(200);
+        config.set_seed(Some(5));
+        config.set_simplify_interval(simplify_interval);
+        let mut gp = GP::with_config(cubic(), &config).unwrap();
+        gp.init();
+        gp.run_for(8, None, |_| true).unwrap();
+        gp
+    };
+    let plain = run(None);
+    let simplified = run(Some(1));
+    let stats = simplified.generation_stats();
+    for pair in stats.windows(2) {
+        assert!(pair[1].best_fitness <= pair[0].best_fitness);
+    }
+    //every generation re-measures the members it simplifies
+    assert!(
+        stats.last().unwrap().fitness_evaluations
+            > plain.generation_stats().last().unwrap().fitness_evaluations
+    );
+    //members with NaN or infinite outputs can measure differently once simplified, and are kept
+    assert!(simplified
+        .population_entries()
+        .iter()
+        .filter(|entry| entry.fitness.is_some())
+        .all(|entry| !has_constant_operation(&entry.expression)));
+
+    let mut config = Config::new();
+    config.set_simplify_interval(Some(0));
+    assert!(config.validate().is_err());
+}
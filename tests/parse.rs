@@ -0,0 +1,133 @@
+mod common;
+
+use common::cubic;
+use wasm_genetic_programming::*;
+
+fn seeded_gp(seed: u64) -> GP {
+    let mut config = Config::new();
+    config.set_pop_size(200);
+    config.set_seed(Some(seed));
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    gp.init();
+    gp
+}
+
+#[test]
+fn printed_expressions_round_trip() {
+    let mut gp = seeded_gp(3);
+    for _ in 0..5 {
+        let result = gp.step().unwrap();
+        let infix = gp.measure_expression(&result.best).unwrap();
+        assert_eq!(infix.expression, result.best);
+        assert_eq!(infix.fitness, result.fitness);
+        assert_eq!(gp.expression_json(&result.best).unwrap(), result.chromosome);
+
+        let json = gp
+            .measure_expression(&result.chromosome.to_string())
+            .unwrap();
+        assert_eq!(json.expression, result.best);
+        assert_eq!(json.fitness, result.fitness);
+    }
+    for entry in gp.population_entries() {
+        assert_eq!(
+            gp.measure_expression(&entry.expression).unwrap().expression,
+            entry.expression
+        );
+    }
+    for expression in ["(x * inf)", "(x - -inf)", "(x + NaN)"] {
+        let json = gp.expression_json(expression).unwrap().to_string();
+        assert_eq!(gp.measure_expression(&json).unwrap().expression, expression);
+    }
+    assert_eq!(
+        gp.expression_json("x - -inf").unwrap()["arg2"]["action"],
+        "-inf"
+    );
+}
+
+#[test]
+fn infix_follows_precedence() {
+    let gp = seeded_gp(1);
+    let parse = |text: &str| gp.measure_expression(text).unwrap().expression;
+    assert_eq!(parse("x + 2 * x"), "(x + (2 * x))");
+    assert_eq!(parse("x - x - 1"), "((x - x) - 1)");
+    assert_eq!(parse("x / (x + 1)"), "(x / (x + 1))");
+    assert_eq!(parse("-x * -2.5e-1"), "((0 - x) * -0.25)");
+    assert_eq!(parse("exp(sin(x), 2)"), "exp(sin(x), 2)");
+    assert_eq!(parse("  cos( x )  "), "cos(x)");
+}
+
+#[test]
+fn bad_input_reports_where() {
+    let gp = seeded_gp(1);
+    let error = |text: &str| gp.measure_expression(text).unwrap_err();
+    assert_eq!(error("(x + 1"), "expected ')' at column 7");
+    assert_eq!(
+        error("x + * 2"),
+        "expected a number, variable or function at column 5"
+    );
+    assert_eq!(
+        error("sin(x, x)"),
+        "sin takes 1 arguments, got 2 at column 1"
+    );
+    assert_eq!(error("x + y"), "unknown variable y at column 5");
+    assert_eq!(
+        error("tanh(x)"),
+        "tanh is not in the function set at column 1"
+    );
+    assert_eq!(error("x x"), "unexpected input at column 3");
+    assert_eq!(error(""), "unexpected end of expression at column 1");
+    assert_eq!(
+        error(r#"{"action": "+", "arg1": {"action": "x"}}"#),
+        "+ is missing arg2"
+    );
+    assert!(error("{").starts_with("invalid JSON"));
+    let nested = format!("{}x{}", "(".repeat(200_000), ")".repeat(200_000));
+    assert_eq!(error(&nested), "expression nested too deeply at column 257");
+    assert!(gp
+        .measure_expression(&format!("{}x", "-".repeat(255)))
+        .is_ok());
+    let negated = format!("{}x", "-".repeat(200_000));
+    assert_eq!(
+        error(&negated),
+        "expression nested too deeply at column 257"
+    );
+}
+
+#[test]
+fn seed_expressions_join_the_initial_population() {
+    let mut config = Config::new();
+    config.set_pop_size(100);
+    config.set_seed(Some(2));
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    gp.seed_expression("3*x*x*x + 2*x*x + x + 1").unwrap();
+    assert!(gp.add_seed_expression("x +").is_err());
+    gp.init();
+    let entries = gp.population_entries();
+    assert_eq!(entries.len(), 100);
+    assert_eq!(entries[0].fitness, Some(0.0));
+    assert_eq!(
+        entries[0].expression,
+        "((((((3 * x) * x) * x) + ((2 * x) * x)) + x) + 1)"
+    );
+    assert_eq!(gp.step().unwrap().stop_reason, Some("target_fitness"));
+    assert!(gp.add_seed_expression("x").is_err());
+}
+
+#[test]
+fn seeds_keep_to_the_depth_limit() {
+    let mut config = Config::new();
+    config.set_tree_limit_initial(3);
+    config.set_tree_limit_running(4);
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    assert_eq!(
+        gp.add_seed_expression("x * x * x * x * x").unwrap_err(),
+        "the seed is 5 deep, more than tree_limit_running (4)"
+    );
+    gp.add_seed_expression("x * x * x * x").unwrap();
+    gp.init();
+    let entries = gp.population_entries();
+    assert!(entries
+        .iter()
+        .any(|entry| entry.expression == "(((x * x) * x) * x)"));
+    assert!(entries.iter().all(|entry| entry.depth <= 4));
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use wasm_genetic_programming::*;
+
+const FUNCTIONS: [&str; 10] = [
+    "+", "-", "*", "/", "exp", "log", "sqrt", "abs", "sin", "min",
+];
+
+fn gp_with_columns(columns: &[&str]) -> GP {
+    common::gp_with_columns(columns, &FUNCTIONS)
+}
+
+#[test]
+fn minimal_infix_reads_back_as_the_same_tree() {
+    let gp = gp_with_columns(&["x", "y"]);
+    let minimal = |expression: &str| gp.render_expression("minimal", Some(expression)).unwrap();
+    assert_eq!(minimal("(x + y) + 3"), "x + y + 3");
+    assert_eq!(minimal("x + (y + 3)"), "x + (y + 3)");
+    assert_eq!(minimal("(x - 1) * y / 2"), "(x - 1) * y / 2");
+    assert_eq!(minimal("x - -3 * exp(x, 2)"), "x - (-3) * exp(x, 2)");
+    for expression in &[
+        "(x + y) + 3",
+        "x / (y / (x - y))",
+        "-3 * x - (y - -2)",
+        "sin(x * (y + 1)) - min(x, NaN)",
+    ] {
+        assert_eq!(
+            gp.render_expression("infix", Some(&minimal(expression))),
+            gp.render_expression("infix", Some(expression))
+        );
+    }
+}
+
+#[test]
+fn latex_uses_mathematical_notation() {
+    let gp = gp_with_columns(&["x", "rate_2"]);
+    let latex = |expression: &str| gp.render_expression("latex", Some(expression)).unwrap();
+    assert_eq!(latex("x + (rate_2 + 3)"), "x + \\mathrm{rate\\_2} + 3");
+    assert_eq!(
+        latex("exp(x + 1, 2) / sqrt(abs(x))"),
+        "\\frac{{\\left(x + 1\\right)}^{2}}{\\sqrt{\\left|x\\right|}}"
+    );
+    assert_eq!(
+        latex("log(x) * sin(x) - -inf"),
+        "\\ln\\left(x\\right) \\cdot \\sin\\left(x\\right) - \\left(-\\infty\\right)"
+    );
+    assert_eq!(
+        latex("min(exp(x, 2), 0.5)"),
+        "\\min\\left({x}^{2}, 0.5\\right)"
+    );
+}
+
+#[test]
+fn latex_escapes_every_name() {
+    let mut gp = gp_with_columns(&["x", "_"]);
+    gp.register_primitive("my_op".to_string(), 1, None, "abs")
+        .unwrap();
+    assert_eq!(
+        gp.render_expression("latex", Some("my_op(x - _)")).unwrap(),
+        "\\operatorname{my\\_op}\\left(x - \\_\\right)"
+    );
+}
+
+#[test]
+fn mathml_is_presentation_markup() {
+    let gp = gp_with_columns(&["x", "a_b"]);
+    assert_eq!(
+        gp.render_expression("mathml", Some("exp(x, 2) / (x - a_b)"))
+            .unwrap(),
+        "<math xmlns=\"http://www.w3.org/1998/Math/MathML\">\
+         <mfrac><msup><mi>x</mi><mrow><mn>2</mn></mrow></msup>\
+         <mrow><mi>x</mi><mo>&#x2212;</mo><mi>a_b</mi></mrow></mfrac></math>"
+    );
+}
+
+#[test]
+fn formats_are_checked_and_default_to_the_best_member() {
+    let mut gp = gp_with_columns(&["x"]);
+    assert_eq!(
+        gp.render_expression("tex", Some("x")).unwrap_err(),
+        "unknown format: tex, expected infix, minimal, latex or mathml"
+    );
+    assert!(gp.render_expression("latex", None).is_err());
+    gp.register_primitive("sq".to_string(), 1, Some("{0}²".to_string()), "abs")
+        .unwrap();
+    assert_eq!(
+        gp.render_expression("latex", Some("sq(x) * 2")).unwrap(),
+        "\\operatorname{sq}\\left(x\\right) \\cdot 2"
+    );
+    gp.init();
+    let best = gp.step().unwrap().best;
+    assert_eq!(gp.render_expression("infix", None).unwrap(), best);
+}
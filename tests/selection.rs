@@ -0,0 +1,77 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use wasm_genetic_programming::selection::*;
+use wasm_genetic_programming::Objective;
+
+const FITNESS: [f64; 5] = [40.0, 3.0, 12.0, 0.5, 7.0];
+
+#[test]
+fn tournament_of_whole_population_picks_best() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let tournament = Tournament {
+        size: 200,
+        objective: Objective::Minimize,
+    };
+    for _ in 0..20 {
+        assert_eq!(tournament.select(&FITNESS, &mut rng), 3);
+    }
+}
+
+#[test]
+fn tournament_respects_maximisation() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let tournament = Tournament {
+        size: 200,
+        objective: Objective::Maximize,
+    };
+    assert_eq!(tournament.select(&FITNESS, &mut rng), 0);
+}
+
+#[test]
+fn roulette_never_picks_non_finite_fitness() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let fitness = [f64::NAN, 1.0, f64::INFINITY, 2.0];
+    let roulette = Roulette {
+        objective: Objective::Minimize,
+    };
+    for index in roulette.select_many(&fitness, 500, &mut rng) {
+        assert!(index == 1 || index == 3);
+    }
+}
+
+#[test]
+fn rank_favours_better_members() {
+    let mut rng = StdRng::seed_from_u64(4);
+    let mut counts = [0; 5];
+    let rank = Rank {
+        objective: Objective::Minimize,
+    };
+    for index in rank.select_many(&FITNESS, 5000, &mut rng) {
+        counts[index] += 1;
+    }
+    assert!(counts[3] > counts[1]);
+    assert!(counts[1] > counts[4]);
+    assert!(counts[4] > counts[2]);
+    assert!(counts[2] > counts[0]);
+}
+
+#[test]
+fn stochastic_universal_returns_requested_count() {
+    let mut rng = StdRng::seed_from_u64(5);
+    let selected = StochasticUniversal {
+        objective: Objective::Minimize,
+    }
+    .select_many(&FITNESS, 12, &mut rng);
+    assert_eq!(selected.len(), 12);
+    assert!(selected.iter().all(|index| *index < FITNESS.len()));
+    //the best member has the largest weight, so it is always hit by some pointer
+    assert!(selected.contains(&3));
+}
+
+#[test]
+fn uniform_stays_in_bounds() {
+    let mut rng = StdRng::seed_from_u64(6);
+    for index in Uniform.select_many(&FITNESS, 100, &mut rng) {
+        assert!(index < FITNESS.len());
+    }
+}
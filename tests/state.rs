@@ -0,0 +1,102 @@
+mod common;
+
+use common::cubic;
+use wasm_genetic_programming::*;
+
+fn new_gp(dedup: &str) -> GP {
+    let mut config = Config::new();
+    config.set_pop_size(200);
+    config.set_seed(Some(9));
+    config.set_dedup(dedup.to_string());
+    config.set_max_generations(12);
+    let mut gp = GP::with_config(cubic(), &config).unwrap();
+    gp.register_primitive("sq".to_string(), 1, None, "abs")
+        .unwrap();
+    gp.seed_expression("x * inf").unwrap();
+    gp
+}
+
+//the remaining results of `gp`, until it is done
+fn finish(gp: &mut GP) -> Vec<String> {
+    let mut results = Vec::new();
+    loop {
+        let result = gp.step().unwrap();
+        results.push(format!("{:?}", result));
+        if result.done {
+            return results;
+        }
+    }
+}
+
+#[test]
+fn restored_runs_continue_exactly() {
+    for dedup in &["off", "structural", "semantic"] {
+        let mut uninterrupted = new_gp(dedup);
+        uninterrupted.init();
+        let expected = finish(&mut uninterrupted);
+
+        let mut saved = new_gp(dedup);
+        saved.init();
+        let mut results: Vec<String> = (0..4)
+            .map(|_| format!("{:?}", saved.step().unwrap()))
+            .collect();
+        let state = saved.save_state();
+        let mut restored = GP::restore_state(&state).unwrap();
+        //everything but the time taken so far reads back exactly
+        let without_time = |state: &str| {
+            let mut state: serde_json::Value = serde_json::from_str(state).unwrap();
+            state.as_object_mut().unwrap().remove("elapsed_ms");
+            state
+        };
+        assert_eq!(without_time(&restored.save_state()), without_time(&state));
+        results.extend(finish(&mut restored));
+
+        assert_eq!(results, expected);
+        assert_eq!(
+            format!("{:?}", restored.generation_stats()),
+            format!("{:?}", uninterrupted.generation_stats())
+        );
+        assert_eq!(
+            format!("{:?}", restored.hall_of_fame_entries()),
+            format!("{:?}", uninterrupted.hall_of_fame_entries())
+        );
+    }
+}
+
+#[test]
+fn state_saved_before_init_keeps_seed_expressions() {
+    let mut uninterrupted = new_gp("off");
+    uninterrupted.init();
+    let mut restored = GP::restore_state(&new_gp("off").save_state()).unwrap();
+    restored.init();
+    assert_eq!(finish(&mut restored), finish(&mut uninterrupted));
+}
+
+#[test]
+fn paused_and_finished_runs_stay_that_way() {
+    let mut gp = new_gp("off");
+    gp.init();
+    gp.step().unwrap();
+    gp.pause();
+    let mut restored = GP::restore_state(&gp.save_state()).unwrap();
+    assert!(restored.paused());
+    restored.resume();
+    restored.cancel();
+    let mut restored = GP::restore_state(&restored.save_state()).unwrap();
+    assert!(!restored.paused());
+    assert_eq!(restored.step().unwrap().stop_reason, Some("cancelled"));
+}
+
+#[test]
+fn bad_states_are_rejected() {
+    let state = new_gp("off").save_state();
+    let error = |state: &str| GP::restore_state(state).err().unwrap();
+    assert!(error("{").starts_with("invalid state"));
+    assert_eq!(
+        error(&state.replacen("\"version\":1", "\"version\":7", 1)),
+        "unsupported state version 7, expected 1"
+    );
+    //the seed expression x * inf, cut short
+    assert!(state.contains("\"f2 v0 cinf\""));
+    assert!(error(&state.replacen("\"f2 v0 cinf\"", "\"f2 v0\"", 1)).contains("malformed"));
+}